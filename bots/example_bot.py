@@ -0,0 +1,63 @@
+# Example external bot. Run the game with:
+#   word-game --bot-command "python bots/example_bot.py"
+# then host a game with the ip "external".
+#
+# Events arrive on stdin as one JSON object per line, commands are written to stdout the same way.
+
+import json
+import queue
+import random
+import sys
+import threading
+import time
+
+TYPING_DELAY = 1.5
+
+events = queue.Queue()
+
+
+def read_events():
+    for line in sys.stdin:
+        events.put(json.loads(line))
+    events.put(None)
+
+
+threading.Thread(target=read_events, daemon=True).start()
+
+board = []
+received = []
+limit = 20
+playing = False
+next_clear = time.time()
+
+while True:
+    try:
+        event = events.get(timeout=0.1)
+    except queue.Empty:
+        event = {"type": "idle"}
+
+    if event is None:
+        break
+    elif event["type"] == "start":
+        playing = True
+    elif event["type"] == "end":
+        playing = False
+    elif event["type"] == "board":
+        board = event["words"]
+        received = event["received"]
+    elif event["type"] == "limit":
+        limit = event["limit"]
+
+    if not playing or time.time() < next_clear:
+        continue
+
+    # defend when close to the limit, attack otherwise
+    if received and len(received) + 3 > limit:
+        command = {"type": "clear", "word": received[0]}
+    elif board:
+        command = {"type": "send", "word": random.choice(board)}
+    else:
+        continue
+
+    print(json.dumps(command), flush=True)
+    next_clear = time.time() + TYPING_DELAY
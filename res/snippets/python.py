@@ -0,0 +1,34 @@
+def greet(name):
+    return f"Hello, {name}!"
+---
+for i in range(10):
+    if i % 2 == 0:
+        print(i)
+---
+squares = [x ** 2 for x in range(8)]
+---
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+---
+with open("words.txt") as f:
+    words = [line.strip() for line in f]
+---
+counts = {}
+for word in words:
+    counts[word] = counts.get(word, 0) + 1
+---
+try:
+    value = int(text)
+except ValueError:
+    value = 0
+---
+import json
+data = json.loads('{"a": 1, "b": [2, 3]}')
+---
+def fib(n):
+    a, b = 0, 1
+    for _ in range(n):
+        a, b = b, a + b
+    return a
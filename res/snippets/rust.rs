@@ -0,0 +1,41 @@
+fn main() {
+    println!("Hello, world!");
+}
+---
+let mut total = 0;
+for x in 1..=10 {
+    total += x * x;
+}
+---
+#[derive(Debug, Clone, PartialEq)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+---
+impl Point {
+    fn length(&self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+}
+---
+let words: Vec<&str> = text.split_whitespace().collect();
+---
+match result {
+    Ok(value) => println!("{value}"),
+    Err(e) => eprintln!("Error: {e}"),
+}
+---
+fn parse(input: &str) -> Result<u32, ParseIntError> {
+    input.trim().parse::<u32>()
+}
+---
+if let Some(first) = items.first() {
+    return first.clone();
+}
+---
+let squares: Vec<u64> = (0..8).map(|n| n * n).collect();
+---
+use std::collections::HashMap;
+let mut counts: HashMap<char, usize> = HashMap::new();
+*counts.entry('a').or_default() += 1;
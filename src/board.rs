@@ -0,0 +1,372 @@
+//! The rules of one player's board, shared by the game, the simulator and the external bots it hosts. Times are
+//! seconds into the game and randomness comes from the caller, so every copy of a board plays the same way.
+
+use std::collections::{HashMap, VecDeque};
+
+use rand::{Rng, seq::SliceRandom};
+
+use crate::powerup::PowerUp;
+use crate::rules::{GameRules, Handicap, MistakePenalty, TextSource};
+use crate::text::same_word;
+use crate::word_game::bonus_word;
+
+#[derive(Debug)]
+pub struct IncomingWord {
+    pub word: String,
+    pub weight: u32,
+    /// seconds into the game at which it lands on the board
+    pub lands_at: f32
+}
+
+/// what a submission cleared and sent
+#[derive(Debug, Default)]
+pub struct Submission {
+    /// every word taken off the board
+    pub cleared: Vec<String>,
+    /// the words to send to the opponent and their weights, after cancelling incoming words
+    pub sent: Vec<(String, u32)>,
+    /// the cleared power-ups that hit the opponent. the others are already applied
+    pub power_ups: Vec<PowerUp>
+}
+
+#[derive(Debug, Default)]
+pub struct Board {
+    pub current_words: Vec<String>,
+    pub received_words: Vec<String>,
+    /// words sent by the opponent that haven't landed yet
+    pub incoming: VecDeque<IncomingWord>,
+    /// how much more than 1 received heavy words count toward the limit
+    pub heavy_words: HashMap<String, u32>,
+    /// builds up by the handicap's received percentage for every word sent to you, a word arrives for each whole 1
+    received_share: f32,
+    /// words on your board that trigger a power-up when cleared
+    pub power_ups: HashMap<String, PowerUp>,
+
+    /// the board can't be cleared until then, after the opponent cleared a freeze
+    pub frozen_until: f32,
+    /// whether the next cleared word is sent twice as many times
+    pub double_send: bool,
+
+    /// submissions that didn't match any word
+    pub mistakes: u32,
+    pub last_mistake: Option<f32>,
+    /// nothing can be submitted until then, after a mistake under the lockout penalty
+    pub locked_until: f32,
+
+    /// clears in a row without typos, each within the combo window of the last
+    pub combo: u32,
+    /// the time of the last clear
+    pub last_clear: f32,
+    /// the time at which the last word was put on the board
+    pub last_new_word: f32
+}
+
+impl Board {
+    /// whether a new word is due on the board. keeps to the spawn schedule however late it's asked
+    pub fn spawn_due(&mut self, rules: &GameRules, time: f32) -> bool {
+        let since_last_word = time - self.last_new_word;
+        if !rules.should_spawn(since_last_word, self.current_words.len()) {
+            return false;
+        }
+
+        self.last_new_word = if since_last_word > rules.spawn_delay {
+            (self.last_new_word + rules.spawn_delay).max(time - rules.spawn_delay)
+        } else {
+            time
+        };
+        true
+    }
+
+    /// puts `list[idx]` on the board, or the next word of the list that isn't on it yet. may give it a power-up
+    pub fn add_word(&mut self, rules: &GameRules, list: &[String], mut idx: usize, rng: &mut impl Rng) {
+        let power_up = (rng.gen::<f32>() < rules.power_ups.chance).then(|| PowerUp::random(rng));
+
+        // snippets are put on the board a line at a time
+        let words_of = |text: &String| match rules.text {
+            TextSource::Code { .. } => text.lines().map(str::to_owned).collect(),
+            _ => vec![text.clone()]
+        };
+
+        for _ in 0..list.len() {
+            if !words_of(&list[idx]).iter().any(|w| self.current_words.contains(w)) {
+                break;
+            }
+            idx = (idx + 1) % list.len();
+        }
+
+        let words: Vec<String> = words_of(&list[idx]);
+        if let (Some(power_up), Some(first)) = (power_up, words.first()) {
+            self.power_ups.insert(first.clone(), power_up);
+        }
+
+        self.current_words.extend(words);
+    }
+
+    /// queues a word sent by the opponent in the incoming lane, unless the handicap drops it
+    pub fn receive(&mut self, rules: &GameRules, handicap: Handicap, word: String, weight: u32, time: f32) {
+        self.received_share += handicap.received_percent as f32 / 100.0;
+        if self.received_share < 1.0 {
+            debug!("Handicap dropped '{}'", word);
+            return;
+        }
+        self.received_share -= 1.0;
+
+        let delay = rules.incoming.delay_secs + handicap.extra_delay_secs;
+        if delay > 0.0 {
+            self.incoming.push_back(IncomingWord { word, weight, lands_at: time + delay });
+        } else {
+            self.land_word(word, weight);
+        }
+    }
+
+    fn land_word(&mut self, word: String, weight: u32) {
+        if weight > 1 {
+            *self.heavy_words.entry(word.clone()).or_default() += weight - 1;
+        }
+
+        self.received_words.push(word);
+    }
+
+    /// moves incoming words whose delay is over onto the board
+    pub fn land_incoming(&mut self, time: f32) {
+        while self.incoming.front().is_some_and(|incoming| incoming.lands_at <= time) {
+            let IncomingWord { word, weight, .. } = self.incoming.pop_front().unwrap();
+            self.land_word(word, weight);
+        }
+    }
+
+    /// how much the received words count toward the limit, with heavy words counting more
+    pub fn received_load(&self) -> usize {
+        self.received_words.len() + self.heavy_words.values().sum::<u32>() as usize
+    }
+
+    pub fn is_frozen(&self, time: f32) -> bool {
+        time < self.frozen_until
+    }
+
+    pub fn is_locked_out(&self, time: f32) -> bool {
+        time < self.locked_until
+    }
+
+    /// whether the strike limit has been reached under the strikes penalty
+    pub fn struck_out(&self, rules: &GameRules) -> bool {
+        match rules.mistakes {
+            MistakePenalty::Strikes { max } => max > 0 && self.mistakes >= max,
+            _ => false
+        }
+    }
+
+    /// the current streak of clears, or 0 once the combo window has passed
+    pub fn combo(&self, rules: &GameRules, time: f32) -> u32 {
+        if time - self.last_clear > rules.combo.window_secs {
+            0
+        } else {
+            self.combo
+        }
+    }
+
+    /// applies the effect of a power-up to this board, whether it was cleared here or by the opponent
+    pub fn apply_power_up(&mut self, rules: &GameRules, power_up: PowerUp, time: f32, rng: &mut impl Rng) {
+        match power_up {
+            PowerUp::Wipe => {
+                self.received_words.clear();
+                self.incoming.clear();
+                self.heavy_words.clear();
+            },
+            PowerUp::Freeze => self.frozen_until = time + rules.power_ups.freeze_secs,
+            PowerUp::DoubleSend => self.double_send = true,
+            PowerUp::Shuffle => {
+                self.current_words.shuffle(rng);
+                self.received_words.shuffle(rng);
+            }
+        }
+    }
+
+    /// counts a mistake and applies the penalty of the rules. garbage words are picked from `list`
+    pub fn punish_mistake(&mut self, rules: &GameRules, list: &[String], time: f32, rng: &mut impl Rng) {
+        self.mistakes += 1;
+        self.last_mistake = Some(time);
+        self.combo = 0;
+
+        match rules.mistakes {
+            MistakePenalty::None | MistakePenalty::Strikes { .. } => {},
+            MistakePenalty::Lockout { secs } => self.locked_until = time + secs,
+            MistakePenalty::Garbage { words } => {
+                for _ in 0..words {
+                    self.land_word(bonus_word(list, 0, rng), 1);
+                }
+            }
+        }
+    }
+
+    /// clears every word matching `text`, from the received words too unless `own_board_only`. the words from your
+    /// own board are sent to the opponent, and streaks of clears send bonus words from `list` as well. clearing
+    /// nothing is a mistake
+    pub fn submit(&mut self, rules: &GameRules, text: &str, own_board_only: bool, list: &[String], time: f32, rng: &mut impl Rng) -> Submission {
+        let mut submission = Submission::default();
+        if self.is_frozen(time) || self.is_locked_out(time) {
+            return submission;
+        }
+
+        let source = &rules.text;
+        let ignore_accents = rules.ignore_accents;
+
+        // received words are always plain words, so only texts on the board can be case sensitive
+        let board_match = |w: &String| source.matches(w, text, ignore_accents);
+        let received_match = |w: &String| !own_board_only && same_word(w, text, ignore_accents);
+        // quotes and snippets have to be typed in order
+        let clearable = if source.in_order() { 1 } else { self.current_words.len() };
+
+        let mut words_to_send = vec![];
+        let sendable = self.current_words.iter().take(clearable).filter(|w| board_match(w))
+            .chain(self.received_words.iter().filter(|w| rules.send.send_received && received_match(w)));
+        for word in sendable {
+            if !words_to_send.contains(word) {
+                words_to_send.push(word.clone());
+            }
+        }
+
+        let mut triggered = vec![];
+        let cleared = self.current_words.iter().take(clearable).filter(|w| board_match(w))
+            .chain(self.received_words.iter().filter(|w| received_match(w)));
+        for word in cleared {
+            submission.cleared.push(word.clone());
+            triggered.extend(self.power_ups.remove(word));
+        }
+
+        let mut index = 0;
+        self.current_words.retain(|w| {
+            index += 1;
+            index > clearable || !board_match(w)
+        });
+        self.received_words.retain(|w| !received_match(w));
+        self.heavy_words.retain(|w, _| !received_match(w));
+
+        let mut bonus = 0;
+        if !submission.cleared.is_empty() {
+            self.combo = self.combo(rules, time) + 1;
+            self.last_clear = time;
+            bonus = rules.combo.bonus(self.combo);
+        } else if !text.is_empty() {
+            self.punish_mistake(rules, list, time, rng);
+        }
+
+        let mut copies = rules.send.copies;
+        if self.double_send && !words_to_send.is_empty() {
+            copies *= 2;
+            self.double_send = false;
+        }
+
+        for word in words_to_send.iter().flat_map(|w| source.attack_words(w)) {
+            for _ in 0..copies {
+                submission.sent.extend(rules.attack.attack(&word, list, rng));
+            }
+        }
+
+        for _ in 0..bonus {
+            submission.sent.push((bonus_word(list, text.chars().count(), rng), 1));
+        }
+
+        if rules.incoming.cancel {
+            let cancelled = submission.sent.len().min(self.incoming.len());
+            if cancelled > 0 {
+                debug!("Cancelled {} incoming words", cancelled);
+            }
+
+            self.incoming.drain(..cancelled);
+            submission.sent.drain(..cancelled);
+        }
+
+        for power_up in triggered {
+            if power_up.targets_opponent() {
+                submission.power_ups.push(power_up);
+            } else {
+                self.apply_power_up(rules, power_up, time, rng);
+            }
+        }
+
+        submission
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::PowerUpRules;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn own_board_only_leaves_received_copies() {
+        let rules = GameRules::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut board = Board { current_words: words(&["apple", "pear"]), received_words: words(&["apple"]), ..Default::default() };
+
+        let submission = board.submit(&rules, "apple", true, &[], 1.0, &mut rng);
+        assert_eq!(submission.cleared, words(&["apple"]));
+        assert_eq!(submission.sent, vec![("apple".to_owned(), 1)]);
+        assert_eq!(board.received_words, words(&["apple"]));
+
+        board.current_words.push("apple".to_owned());
+        let submission = board.submit(&rules, "Apple", false, &[], 2.0, &mut rng);
+        assert_eq!(submission.cleared, words(&["apple", "apple"]));
+        assert_eq!(submission.sent.len(), 1);
+        assert_eq!((board.current_words.clone(), board.received_words.len(), board.combo), (words(&["pear"]), 0, 2));
+    }
+
+    #[test]
+    fn mistakes_lock_the_board() {
+        let rules = GameRules { mistakes: MistakePenalty::Lockout { secs: 1.0 }, ..Default::default() };
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut board = Board { current_words: words(&["apple"]), combo: 3, last_clear: 1.0, ..Default::default() };
+
+        let submission = board.submit(&rules, "appel", false, &[], 1.5, &mut rng);
+        assert!(submission.cleared.is_empty());
+        assert_eq!((board.mistakes, board.combo, board.last_mistake), (1, 0, Some(1.5)));
+
+        assert!(board.submit(&rules, "apple", false, &[], 2.0, &mut rng).cleared.is_empty());
+        assert_eq!(board.submit(&rules, "apple", false, &[], 2.5, &mut rng).cleared, words(&["apple"]));
+    }
+
+    #[test]
+    fn snippets_are_added_a_line_at_a_time() {
+        let rules = GameRules {
+            text: TextSource::Code { file: String::new(), words_per_send: 1 },
+            power_ups: PowerUpRules { chance: 1.0, ..Default::default() },
+            ..Default::default()
+        };
+        let snippets = words(&["let a = 1;\nlet b = a;", "fn main() {}"]);
+        let mut board = Board { current_words: words(&["let b = a;"]), ..Default::default() };
+
+        // the first snippet already has a line on the board
+        board.add_word(&rules, &snippets, 0, &mut StdRng::seed_from_u64(0));
+        assert_eq!(board.current_words, words(&["let b = a;", "fn main() {}"]));
+        assert_eq!(board.power_ups.keys().collect::<Vec<_>>(), vec!["fn main() {}"]);
+
+        board.current_words.clear();
+        board.power_ups.clear();
+        board.add_word(&rules, &snippets, 0, &mut StdRng::seed_from_u64(0));
+        assert_eq!(board.current_words, words(&["let a = 1;", "let b = a;"]));
+        assert_eq!(board.power_ups.keys().collect::<Vec<_>>(), vec!["let a = 1;"]);
+    }
+
+    #[test]
+    fn handicaps_drop_and_delay_received_words() {
+        let rules = GameRules::default();
+        let handicap = Handicap { received_percent: 50, extra_delay_secs: 1.0, ..Default::default() };
+        let mut board = Board::default();
+
+        for word in ["a", "b", "c", "d"] {
+            board.receive(&rules, handicap, word.to_owned(), 2, 0.0);
+        }
+        board.land_incoming(0.5);
+        assert!(board.received_words.is_empty());
+
+        board.land_incoming(1.0);
+        assert_eq!(board.received_words, words(&["b", "d"]));
+        assert_eq!(board.received_load(), 4);
+    }
+}
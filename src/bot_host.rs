@@ -0,0 +1,298 @@
+//! Hosts bots written in other languages. The bot is launched as a child process and plays its own
+//! board, talking newline-delimited JSON over its stdin/stdout. The command is split like a shell would,
+//! so paths with spaces can be quoted: `--bot-command "python3 'my bots/bot.py'"`.
+//!
+//! Events written to the bot's stdin:
+//! ```text
+//! {"type":"start"}
+//! {"type":"board","words":["Apple",...],"received":["Pear",...]}
+//! {"type":"received","word":"Pear","weight":1}   // heavy words count more toward the limit
+//! {"type":"limit","limit":20}
+//! {"type":"end","outcome":"win"}       // "win", "loss" or "time_up" at the end of a time attack
+//! ```
+//!
+//! Commands read from the bot's stdout:
+//! ```text
+//! {"type":"clear","word":"apple"}
+//! {"type":"send","word":"apple"}
+//! ```
+//! Clearing works exactly like pressing enter in the game: matching words are removed from both lists,
+//! and the ones that were on the bot's own board are sent to the opponent. Sending only takes the word
+//! off the bot's own board and sends it, leaving any received copies of it where they are. Either one counts as
+//! a mistake if it clears nothing.
+
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::TcpStream;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+use std::thread;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::board::Board;
+use crate::network::{connect_to_local_bot, BotMatch, Connection, Packet, DUMMY_IP, DUMMY_PORT};
+use crate::rules::{GameRules, Handicap};
+use crate::word_game::GameMode;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotEvent<'a> {
+    Start,
+    Board {
+        words: &'a [String],
+        received: &'a [String]
+    },
+    Received {
+        word: &'a str,
+        weight: u32
+    },
+    Limit {
+        limit: usize
+    },
+    End {
+        outcome: &'static str
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BotCommand {
+    Clear {
+        word: String
+    },
+    Send {
+        word: String
+    }
+}
+
+/// the board of the external bot, updated by the same rules as the player's own board
+struct BotBoard {
+    start_time: Instant,
+    board: Board,
+    limit: usize
+}
+
+impl BotBoard {
+    fn new(rules: &GameRules) -> Self {
+        BotBoard {
+            start_time: Instant::now(),
+            board: Board::default(),
+            limit: rules.limit.start
+        }
+    }
+
+    fn time(&self) -> f32 {
+        self.start_time.elapsed().as_secs_f32()
+    }
+
+    /// clears `word` from the board, and from the received words too unless `own_board_only`, sending the
+    /// cleared words that can be sent to the opponent
+    fn clear(&mut self, word: &str, own_board_only: bool, bot_match: &mut BotMatch, conn: &mut Connection, words: &[String], rng: &mut impl Rng) -> io::Result<()> {
+        let time = self.time();
+        let submission = self.board.submit(&bot_match.rules, word, own_board_only, words, time, rng);
+
+        for w in submission.cleared.iter() {
+            bot_match.cleared(conn, w)?;
+        }
+
+        for (word, weight) in submission.sent {
+            conn.send_packet(Packet::attack(&word, weight))?;
+        }
+
+        for power_up in submission.power_ups {
+            conn.send_packet(Packet::PowerUp { power_up })?;
+        }
+
+        Ok(())
+    }
+}
+
+struct BotProcess {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    commands: Receiver<String>
+}
+
+impl BotProcess {
+    fn spawn(command: &str) -> io::Result<Self> {
+        let parts = shlex::split(command)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Unbalanced quotes in bot command {command}")))?;
+        let (program, args) = parts.split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty bot command"))?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin = BufWriter::new(child.stdin.take().unwrap());
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        let (sender, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                match line {
+                    Ok(line) => if sender.send(line).is_err() { break; },
+                    Err(e) => {
+                        warn!("Failed to read from bot: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(BotProcess { child, stdin, commands })
+    }
+
+    fn send(&mut self, event: BotEvent) -> io::Result<()> {
+        serde_json::to_writer(&mut self.stdin, &event)?;
+        self.stdin.write_all(b"\n")?;
+        self.stdin.flush()
+    }
+
+    fn send_board(&mut self, board: &BotBoard) -> io::Result<()> {
+        self.send(BotEvent::Board { words: &board.board.current_words, received: &board.board.received_words })
+    }
+
+    /// returns the next command from the bot, if there is one
+    fn poll_command(&mut self) -> io::Result<Option<BotCommand>> {
+        loop {
+            match self.commands.try_recv() {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => match serde_json::from_str(&line) {
+                    Ok(command) => return Ok(Some(command)),
+                    Err(e) => warn!("Ignoring invalid bot command '{}': {}", line, e)
+                },
+                Err(TryRecvError::Empty) => return Ok(None),
+                Err(TryRecvError::Disconnected) => {
+                    return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Bot closed its output"));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for BotProcess {
+    fn drop(&mut self) {
+        if let Err(e) = self.child.kill() {
+            warn!("Failed to kill bot process: {}", e);
+        }
+    }
+}
+
+fn host_bot(bot: &mut BotProcess, conn: &mut Connection, words: &[String]) -> io::Result<()> {
+    let mut rng = rand::thread_rng();
+    let mut bot_match = BotMatch::new();
+    let mut board = BotBoard::new(&bot_match.rules);
+
+    bot.send(BotEvent::Start)?;
+    bot.send(BotEvent::Limit { limit: board.limit })?;
+
+    loop {
+        if !bot_match.over {
+            let time = board.time();
+            if board.board.spawn_due(&bot_match.rules, time) {
+                let idx = rng.gen_range(0..words.len());
+                board.board.add_word(&bot_match.rules, words, idx, &mut rng);
+                bot.send_board(&board)?;
+            }
+
+            let landed = board.board.received_words.len();
+            board.board.land_incoming(time);
+            if board.board.received_words.len() != landed {
+                bot.send_board(&board)?;
+            }
+
+            let limit = bot_match.rules.limit.limit_at(time);
+            if limit != board.limit {
+                board.limit = limit;
+                bot.send(BotEvent::Limit { limit })?;
+            }
+
+            while let Some(command) = bot.poll_command()? {
+                match command {
+                    BotCommand::Clear { word } => board.clear(&word, false, &mut bot_match, conn, words, &mut rng)?,
+                    BotCommand::Send { word } => board.clear(&word, true, &mut bot_match, conn, words, &mut rng)?
+                }
+
+                bot.send_board(&board)?;
+            }
+
+            bot_match.update(conn)?;
+
+            if bot_match.over {
+                bot.send(BotEvent::End { outcome: "time_up" })?;
+            } else if bot_match.mode == GameMode::Versus && (board.board.received_load() > board.limit || board.board.struck_out(&bot_match.rules)) {
+                conn.send_packet(Packet::ILost {})?;
+                bot.send(BotEvent::End { outcome: "loss" })?;
+                bot_match.over = true;
+            }
+        } else {
+            // commands sent after the end of a match are meaningless
+            while bot.poll_command()?.is_some() {}
+        }
+
+        let packet = match conn.poll_next_packet()? {
+            None => {
+                thread::sleep(Duration::from_millis(10));
+                continue;
+            },
+            Some(packet) => packet
+        };
+
+        let was_over = bot_match.over;
+        if bot_match.handle_packet(conn, &packet)? || matches!(packet, Packet::MatchSettings { .. }) {
+            if let Packet::WaitingToRestart = packet {
+                conn.send_packet(Packet::WaitingToRestart)?;
+            }
+
+            board = BotBoard::new(&bot_match.rules);
+            bot.send(BotEvent::Start)?;
+            bot.send(BotEvent::Limit { limit: board.limit })?;
+            continue;
+        }
+
+        match packet {
+            Packet::AddWord { word, weight } if !was_over => {
+                bot.send(BotEvent::Received { word: &word, weight })?;
+                let time = board.time();
+                // bots never play with a handicap
+                board.board.receive(&bot_match.rules, Handicap::default(), word, weight, time);
+                bot.send_board(&board)?;
+            },
+            Packet::PowerUp { power_up } if !was_over => {
+                let time = board.time();
+                board.board.apply_power_up(&bot_match.rules, power_up, time, &mut rng);
+                bot.send_board(&board)?;
+            },
+            Packet::ILost {  } if !was_over => {
+                bot.send(BotEvent::End { outcome: "win" })?;
+            },
+            p => debug!("External bot ignoring {:?}", p)
+        }
+    }
+}
+
+fn run_external_bot(mut bot: BotProcess, words: Vec<String>) {
+    let stream = TcpStream::connect((DUMMY_IP, DUMMY_PORT)).unwrap();
+    let mut conn = Connection::new(stream).unwrap();
+
+    if let Err(e) = host_bot(&mut bot, &mut conn, &words) {
+        error!("External bot stopped: {}", e);
+    }
+}
+
+/// launches `command` as an external bot and connects to it, like [`crate::network::connect_to_dummy`]
+pub fn connect_to_external_bot(command: &str, words: Vec<String>) -> io::Result<Connection> {
+    if words.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "No words to put on the bot's board"));
+    }
+
+    info!("Launching external bot '{}'", command);
+    let bot = BotProcess::spawn(command)?;
+
+    connect_to_local_bot(move || run_external_bot(bot, words))
+}
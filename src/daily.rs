@@ -0,0 +1,190 @@
+//! The daily challenge: a survival run seeded by the date, so everyone gets the same run on the same day.
+//! Results are kept in `scores/daily.txt` and compared with `word-game daily <score string>...`.
+
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::fmt;
+
+use crate::survival::SurvivalScore;
+use crate::user_data;
+
+pub const DAILY_SCORES: &str = "scores/daily.txt";
+const EXPORT_PREFIX: &str = "daily";
+
+/// days since the unix epoch, in UTC so that everyone agrees on the day
+pub fn today() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() / 86400)
+}
+
+/// the seed of the challenge on `day`
+pub fn seed(day: u64) -> u64 {
+    // splitmix64, so that neighbouring days get unrelated seeds
+    let mut z = day.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// `day` as yyyy-mm-dd
+pub fn date(day: u64) -> String {
+    // from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = day as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + (m <= 2) as i64;
+
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// the day of a yyyy-mm-dd date
+pub fn parse_date(date: &str) -> Option<u64> {
+    let mut parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (y, m, d) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+
+    // from http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    u64::try_from(era * 146097 + doe - 719468).ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DailyScore {
+    pub day: u64,
+    pub score: SurvivalScore
+}
+
+impl DailyScore {
+    /// a string to share the result with, `daily/<date>/<secs>/<wave>/<words>/<check>`.
+    /// the check only catches typos, it doesn't stop anyone from making up a score
+    pub fn export(&self) -> String {
+        let body = format!(
+            "{EXPORT_PREFIX}/{}/{:.1}/{}/{}",
+            date(self.day), self.score.time, self.score.wave, self.score.words
+        );
+        let check = checksum(&body);
+
+        format!("{body}/{check:04x}")
+    }
+
+    pub fn parse_export(text: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Invalid daily score '{text}'"));
+
+        let text = text.trim();
+        let (body, check) = text.rsplit_once('/').ok_or_else(invalid)?;
+        if u16::from_str_radix(check, 16).ok() != Some(checksum(body)) {
+            return Err(invalid());
+        }
+
+        match body.split('/').collect::<Vec<_>>()[..] {
+            [EXPORT_PREFIX, day, time, wave, words] => Ok(DailyScore {
+                day: parse_date(day).ok_or_else(invalid)?,
+                score: SurvivalScore {
+                    time: time.parse().map_err(|_| invalid())?,
+                    wave: wave.parse().map_err(|_| invalid())?,
+                    words: words.parse().map_err(|_| invalid())?
+                }
+            }),
+            _ => Err(invalid())
+        }
+    }
+
+    pub fn parse(line: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Invalid daily score '{line}'"));
+        let (day, score) = line.split_once(' ').ok_or_else(invalid)?;
+
+        Ok(DailyScore {
+            day: day.parse().map_err(|_| invalid())?,
+            score: SurvivalScore::parse(score)?
+        })
+    }
+}
+
+/// Format: `<day> <secs> <wave> <words>`
+impl fmt::Display for DailyScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.day, self.score)
+    }
+}
+
+fn checksum(text: &str) -> u16 {
+    // fnv-1a, folded to 16 bits
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+    (hash ^ (hash >> 16) ^ (hash >> 32) ^ (hash >> 48)) as u16
+}
+
+/// every daily run played, oldest first
+pub fn load_all() -> io::Result<Vec<DailyScore>> {
+    let text = match user_data::read_to_string(DAILY_SCORES) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e)
+    };
+
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(DailyScore::parse)
+        .collect()
+}
+
+/// saves a run, returning the best run of that day so far, which may be this one
+pub fn record(score: DailyScore) -> io::Result<DailyScore> {
+    let mut all = load_all().unwrap_or_else(|e| {
+        warn!("Replacing unreadable daily scores: {}", e);
+        vec![]
+    });
+    all.push(score);
+
+    user_data::write(DAILY_SCORES, all.iter().map(|s| format!("{s}\n")).collect::<String>())?;
+
+    Ok(all.into_iter()
+        .filter(|s| s.day == score.day)
+        .max_by(|a, b| a.score.time.total_cmp(&b.score.time))
+        .unwrap_or(score))
+}
+
+/// `word-game daily [score string]...` ranks score strings by day and time survived, or lists your own best runs
+pub fn run_cli(args: &[String]) -> io::Result<()> {
+    let mut scores = if args.is_empty() {
+        let mut best: Vec<DailyScore> = vec![];
+        for score in load_all()? {
+            match best.iter_mut().find(|b| b.day == score.day) {
+                Some(b) if b.score.time < score.score.time => *b = score,
+                Some(_) => {},
+                None => best.push(score)
+            }
+        }
+        best
+    } else {
+        args.iter().map(|a| DailyScore::parse_export(a)).collect::<io::Result<_>>()?
+    };
+
+    scores.sort_by(|a, b| b.day.cmp(&a.day).then(b.score.time.total_cmp(&a.score.time)));
+
+    let mut last_day = None;
+    for score in scores {
+        if last_day != Some(score.day) {
+            println!("{}", date(score.day));
+            last_day = Some(score.day);
+        }
+
+        let secs = score.score.time as u64;
+        println!(
+            "  {}:{:02} wave {} ({} words)  {}",
+            secs / 60, secs % 60, score.score.wave, score.score.words, score.export()
+        );
+    }
+
+    Ok(())
+}
@@ -1,42 +1,85 @@
-use std::{collections::HashSet, time::Instant};
-
 use ggez::{
     event::EventHandler,
     glam::Vec2,
-    graphics::{self, Color, DrawMode, StrokeOptions, Text, TextFragment},
+    graphics::{self, Color, DrawMode, Rect, StrokeOptions, Text, TextFragment},
     input::keyboard::KeyMods,
     winit::event::VirtualKeyCode,
     Context, GameResult,
 };
 
 use crate::{
+    daily,
     network::Packet,
+    powerup::PowerUp,
+    rules::{MistakePenalty, TextSource, HANDICAPS},
+    text,
+    tournament::{Format, MIN_PLAYERS, TOURNAMENT_PORT},
     render::{
         center_text_in_rect, cut_bottom, cut_left, cut_right, cut_top,
-        render_words_in_rect, shrink, LIGHT_TEXT_COLOR, TEXT_COLOR, WINDOW_BG,
+        render_words_in_rect, WordStyle, render_quote_in_rect, render_bracket, render_code_in_rect, typed_text_with_correctness, shrink, lerp_color, LIGHT_TEXT_COLOR, TEXT_COLOR, WINDOW_BG,
+    },
+    word_game::{
+        WordGame, GameState, GameOutcome, GameMode, StateTransition, ScoreBy,
+        MenuOption, MENU_OPTIONS,
     },
-    word_game::{WordGame, GameState, GameOutcome, OngoingGame, StateTransition},
 };
 
 pub const MARGIN: f32 = 10.0;
+const INCOMING_LANE_HEIGHT: f32 = 70.0;
+const INCOMING_COLOR: Color = Color::new(1.0, 0.5, 0.0, 1.0);
+const MISTAKE_FLASH_SECS: f32 = 0.4;
+/// the input box only shows the end of longer texts
+const MAX_INPUT_CHARS: usize = 30;
+/// background of received words that weigh more than 1
+const HEAVY_COLOR: Color = Color::new(1.0, 0.3, 0.3, 0.3);
 
 impl EventHandler for WordGame {
     fn update(&mut self, _ctx: &mut Context) -> GameResult {
+        self.reload_changed_word_list();
+        if let GameState::Ongoing(ref mut ongoing) = self.state {
+            ongoing.tick();
+        }
         self.process_network()?;
 
         match self.state {
             GameState::Ongoing(ref mut ongoing) => {
-                if ((Instant::now() - ongoing.last_new_word).as_secs_f32() > 0.3
-                    || ongoing.current_words.len() < 5)
-                    && ongoing.current_words.len() < 20
-                {
-                    ongoing.add_new_word(&self.word_list);
-                    ongoing.last_new_word = Instant::now();
-                }
+                ongoing.land_incoming();
+
+                let (list, difficulties) = match ongoing.mode {
+                    GameMode::Daily { .. } => (&self.daily_word_list, &[][..]),
+                    _ => (&self.word_list, &self.word_difficulties[..])
+                };
+                ongoing.spawn_words(list, difficulties);
+
+                match ongoing.mode {
+                    GameMode::Versus => if ongoing.received_load() > ongoing.limit() || ongoing.struck_out() {
+                        ongoing.send_packet(Packet::ILost {})?;
+                        self.queue_transition(StateTransition::LoseGame);
+                    },
+                    GameMode::TimeAttack { .. } => if ongoing.is_time_up() {
+                        if !ongoing.done {
+                            ongoing.send_packet(Packet::Score { score: ongoing.score, done: true })?;
+                            ongoing.done = true;
+                        }
 
-                if ongoing.received_words.len() > ongoing.limit() {
-                    ongoing.conn.send_packet(Packet::ILost {})?;
-                    self.queue_transition(StateTransition::LoseGame);
+                        if ongoing.opponent_done {
+                            self.queue_transition(StateTransition::FinishTimeAttack);
+                        }
+                    },
+                    GameMode::Practice { .. } => if ongoing.is_time_up() || ongoing.struck_out() {
+                        self.queue_transition(StateTransition::FinishPractice);
+                    },
+                    GameMode::Survival | GameMode::Daily { .. } => {
+                        ongoing.send_wave_words(list);
+
+                        if ongoing.received_load() > ongoing.limit() || ongoing.struck_out() {
+                            let transition = match ongoing.mode {
+                                GameMode::Daily { .. } => StateTransition::FinishDaily,
+                                _ => StateTransition::FinishSurvival
+                            };
+                            self.queue_transition(transition);
+                        }
+                    }
                 }
             },
             GameState::Ended { waiting_to_restart: true, opponent_waiting_to_restart: true, ..} => {
@@ -60,20 +103,54 @@ impl EventHandler for WordGame {
             GameState::Ongoing(ongoing) => {
                 let (word_region, write_region) = cut_bottom(draw_region, 75.0);
 
+                // shake the input box and flash it red right after a mistake
+                let mistake_flash = ongoing.board.last_mistake
+                    .map(|at| (ongoing.time() - at) / MISTAKE_FLASH_SECS)
+                    .filter(|t| *t < 1.0);
+                let input_region = match mistake_flash {
+                    Some(t) => Rect { x: write_region.x + (t * 40.0).sin() * 12.0 * (1.0 - t), ..write_region },
+                    None => write_region
+                };
+
+                if let Some(t) = mistake_flash {
+                    canvas.draw(
+                        &graphics::Mesh::new_rounded_rectangle(
+                            ctx,
+                            DrawMode::fill(),
+                            input_region,
+                            10.0,
+                            Color::new(1.0, 0.0, 0.0, 0.3 * (1.0 - t)),
+                        )
+                        .unwrap(),
+                        Vec2::new(0.0, 0.0),
+                    );
+                }
+
                 center_text_in_rect(
                     ctx,
                     &mut canvas,
-                    &Text::new(match (&ongoing.current_text).as_str() {
-                        "" => TextFragment::new("Start typing...")
-                            .color(LIGHT_TEXT_COLOR)
+                    &match (&ongoing.current_text).as_str() {
+                        _ if ongoing.is_frozen() => Text::new(TextFragment::new("Frozen!")
+                            .color(PowerUp::Freeze.color())
                             .scale(70.0)
-                            .font("courier_new"),
-                        _ => TextFragment::new(ongoing.current_text.clone()+"|")
-                            .color(TEXT_COLOR)
+                            .font("courier_new")),
+                        _ if ongoing.is_locked_out() => Text::new(TextFragment::new("Locked!")
+                            .color(Color::RED)
                             .scale(70.0)
-                            .font("courier_new"),
-                    }),
-                    write_region,
+                            .font("courier_new")),
+                        "" => Text::new(TextFragment::new("Start typing...")
+                            .color(LIGHT_TEXT_COLOR)
+                            .scale(70.0)
+                            .font("courier_new")),
+                        typed => match ongoing.typing_target() {
+                            Some(target) => typed_text_with_correctness(typed, target, MAX_INPUT_CHARS, "courier_new", 70.0),
+                            None => Text::new(TextFragment::new(ongoing.current_text.clone()+"|")
+                                .color(TEXT_COLOR)
+                                .scale(70.0)
+                                .font("courier_new")),
+                        },
+                    },
+                    input_region,
                 );
 
                 center_text_in_rect(
@@ -88,13 +165,49 @@ impl EventHandler for WordGame {
                     cut_right(write_region, 200.0).1,
                 );
 
+                if let GameMode::TimeAttack { score_by, .. } = ongoing.mode {
+                    center_text_in_rect(
+                        ctx,
+                        &mut canvas,
+                        &Text::new(
+                            TextFragment::new(format!(
+                                "{} v {}",
+                                ongoing.score.get(score_by),
+                                ongoing.opponent_score.get(score_by)
+                            ))
+                                .color(TEXT_COLOR)
+                                .scale(50.0)
+                                .font("courier_new"),
+                        ),
+                        cut_left(write_region, 200.0).0,
+                    );
+                }
+
+                if ongoing.mode.has_waves() {
+                    let survived = ongoing.start_time.elapsed().as_secs();
+                    center_text_in_rect(
+                        ctx,
+                        &mut canvas,
+                        &Text::new(
+                            TextFragment::new(format!("wave {}\n{}:{:02}", ongoing.wave(), survived / 60, survived % 60))
+                                .color(TEXT_COLOR)
+                                .scale(35.0)
+                                .font("courier_new"),
+                        ),
+                        cut_left(write_region, 200.0).0,
+                    );
+                }
+
                 canvas.draw(
                     &graphics::Mesh::new_rounded_rectangle(
                         ctx,
                         DrawMode::Stroke(StrokeOptions::default().with_line_width(3.0)),
-                        write_region,
+                        input_region,
                         10.0,
-                        Color::BLACK,
+                        match mistake_flash {
+                            Some(t) => lerp_color(Color::RED, Color::BLACK, t),
+                            None => Color::BLACK
+                        },
                     )
                     .unwrap(),
                     Vec2::new(0.0, 0.0),
@@ -110,11 +223,18 @@ impl EventHandler for WordGame {
                 let (received_word_region_header, received_word_region) =
                     cut_top(received_word_region, 80.0);
 
+                let (incoming_region, received_word_region) = if ongoing.rules.incoming.delay_secs > 0.0 {
+                    let (incoming_region, rest) = cut_top(received_word_region, INCOMING_LANE_HEIGHT);
+                    (Some(incoming_region), cut_top(rest, MARGIN).1)
+                } else {
+                    (None, received_word_region)
+                };
+
                 center_text_in_rect(
                     ctx,
                     &mut canvas,
                     &Text::new(
-                        TextFragment::new("Your Words")
+                        TextFragment::new(if ongoing.board.double_send { "Your Words x2" } else { "Your Words" })
                             .color(Color::BLACK)
                             .scale(80.0)
                             .font("courier_new"),
@@ -122,23 +242,69 @@ impl EventHandler for WordGame {
                     current_word_region_header,
                 );
 
-                let limit = ongoing.limit();
-                let exclamation_mark_count = if ongoing.received_words.len() <= limit - 5 {
-                    0
-                } else {
-                    (ongoing.received_words.len() + 5 - limit).min(5)
+                if let MistakePenalty::Strikes { max } = ongoing.rules.mistakes {
+                    center_text_in_rect(
+                        ctx,
+                        &mut canvas,
+                        &Text::new(
+                            TextFragment::new(format!("strikes\n{}/{}", ongoing.board.mistakes, max))
+                                .color(if ongoing.board.mistakes > 0 { Color::RED } else { TEXT_COLOR })
+                                .scale(35.0)
+                                .font("courier_new"),
+                        ),
+                        cut_left(current_word_region_header, 180.0).0,
+                    );
+                }
+
+                let combo = ongoing.combo();
+                if combo >= 2 {
+                    let bonus = ongoing.rules.combo.bonus(combo);
+                    center_text_in_rect(
+                        ctx,
+                        &mut canvas,
+                        &Text::new(
+                            TextFragment::new(if bonus > 0 {
+                                format!("{} combo\n+{}", combo, bonus)
+                            } else {
+                                format!("{} combo", combo)
+                            })
+                                .color(if bonus > 0 { Color::RED } else { TEXT_COLOR })
+                                .scale(35.0)
+                                .font("courier_new"),
+                        ),
+                        cut_right(current_word_region_header, 180.0).1,
+                    );
+                }
+
+                let received_header = match ongoing.time_left() {
+                    Some(_) if ongoing.done => "waiting...".to_owned(),
+                    Some(left) => {
+                        let left = left.max(0.0).ceil() as u64;
+                        format!("{}:{:02}", left / 60, left % 60)
+                    },
+                    None => {
+                        let limit = ongoing.limit();
+                        let load = ongoing.received_load();
+                        let exclamation_mark_count = if load + 5 <= limit {
+                            0
+                        } else {
+                            (load + 5 - limit).min(5)
+                        };
+
+                        format!(
+                            "{}/{}{}",
+                            load,
+                            limit,
+                            "!".repeat(exclamation_mark_count)
+                        )
+                    }
                 };
 
                 center_text_in_rect(
                     ctx,
                     &mut canvas,
                     &Text::new(
-                        TextFragment::new(&format!(
-                            "{}/{}{}",
-                            ongoing.received_words.len(),
-                            limit,
-                            "!".repeat(exclamation_mark_count)
-                        ))
+                        TextFragment::new(received_header)
                         .color(Color::BLACK)
                         .scale(80.0)
                         .font("courier_new"),
@@ -146,33 +312,97 @@ impl EventHandler for WordGame {
                     received_word_region_header,
                 );
 
+                if let TextSource::Quotes { .. } = ongoing.rules.text {
+                    render_quote_in_rect(
+                        ctx,
+                        &mut canvas,
+                        ongoing.board.current_words.first().map_or("", String::as_str),
+                        &ongoing.current_text,
+                        current_word_region,
+                        "courier_new",
+                        40.0,
+                    );
+                } else if let TextSource::Code { .. } = ongoing.rules.text {
+                    render_code_in_rect(
+                        ctx,
+                        &mut canvas,
+                        &ongoing.board.current_words,
+                        &ongoing.current_text,
+                        current_word_region,
+                        "courier_new",
+                        30.0,
+                    );
+                } else {
+                    render_words_in_rect(
+                        ctx,
+                        &mut canvas,
+                        &ongoing.board.current_words,
+                        current_word_region,
+                        WordStyle {
+                            font: "courier_new",
+                            font_size: 50.0,
+                            color: Color::BLACK,
+                            cross_out: &|word| ongoing.typed_prefix(word),
+                            highlight: &|word| ongoing.board.power_ups.get(word).map(|p| p.color())
+                        },
+                    );
+                }
                 render_words_in_rect(
                     ctx,
                     &mut canvas,
-                    &ongoing.current_words,
-                    current_word_region,
-                    "courier_new",
-                    50.0,
-                    &ongoing.current_text,
-                    Color::BLACK,
-                );
-                render_words_in_rect(
-                    ctx,
-                    &mut canvas,
-                    &ongoing.received_words,
+                    &ongoing.board.received_words,
                     received_word_region,
-                    "courier_new",
-                    50.0,
-                    &ongoing.current_text,
-                    Color::RED,
+                    WordStyle {
+                        font: "courier_new",
+                        font_size: 50.0,
+                        color: Color::RED,
+                        cross_out: &|word| ongoing.typed_prefix(word),
+                        highlight: &|word| ongoing.board.heavy_words.contains_key(word).then_some(HEAVY_COLOR)
+                    },
                 );
+
+                if let Some(incoming_region) = incoming_region {
+                    render_words_in_rect(
+                        ctx,
+                        &mut canvas,
+                        &ongoing.board.incoming.iter().map(|incoming| incoming.word.clone()).collect(),
+                        incoming_region,
+                        WordStyle {
+                            font: "courier_new",
+                            font_size: 30.0,
+                            color: INCOMING_COLOR,
+                            cross_out: &|_| None,
+                            highlight: &|word| ongoing.board.incoming.iter().any(|i| i.word == word && i.weight > 1).then_some(HEAVY_COLOR)
+                        },
+                    );
+                }
             },
-            GameState::Ended { outcome, waiting_to_restart, opponent_waiting_to_restart, wpm,  .. } => {
+            GameState::Ended { rules, handicaps, outcome, waiting_to_restart, opponent_waiting_to_restart, wpm, scores, .. } => {
                 //TODO: Improve this screen lol
 
                 let text = match outcome {
                     GameOutcome::Win => "You won!",
-                    GameOutcome::Loss => "You lost!"
+                    GameOutcome::Loss => "You lost!",
+                    GameOutcome::Draw => "Draw!"
+                };
+
+                let score_comparison = match (rules.mode(), scores) {
+                    (GameMode::TimeAttack { score_by, .. }, Some((mine, theirs))) => format!(
+                        "{} v {} {}\n",
+                        mine.get(score_by),
+                        theirs.get(score_by),
+                        match score_by {
+                            ScoreBy::Words => "words",
+                            ScoreBy::Characters => "chars"
+                        }
+                    ),
+                    _ => String::new()
+                };
+
+                let handicap_info = if *handicaps == Default::default() {
+                    String::new()
+                } else {
+                    format!("handicaps: you {}, opponent {}\n", handicaps.0.name, handicaps.1.name)
                 };
 
                 center_text_in_rect(
@@ -192,7 +422,10 @@ impl EventHandler for WordGame {
                     &mut canvas,
                     &Text::new(
                         TextFragment::new(format!(
-                            "press n to change ip\n{:.2}wpm\n{}",
+                            "press n to change ip, m for menu\n{}{}words: {}\n{:.2}wpm\n{}",
+                            score_comparison,
+                            handicap_info,
+                            self.word_list_name,
                             wpm,
                             match (opponent_waiting_to_restart, waiting_to_restart) {
                                 (false, false) => "press r to restart",
@@ -208,8 +441,8 @@ impl EventHandler for WordGame {
                     cut_top(draw_region, draw_region.h / 2.0).1,
                 );
             },
-            GameState::ConnectionConfig { input_y, host, ip, port } => {
-                let height = draw_region.h / 4.0;
+            GameState::ConnectionConfig { input_y, host, ip, port, rules, tournament } => {
+                let height = draw_region.h / 7.0;
                 let cursor1 = if (self.create_time.elapsed().as_secs_f32() * 2.0).round() % 2.0
                     == 0.0
                     && *input_y == 1
@@ -226,32 +459,218 @@ impl EventHandler for WordGame {
                 } else {
                     " "
                 };
-                for i in [
-                    (format!("host: {:?}", host), cut_top(draw_region, height).0),
-                    (
-                        format!("ip: {:}{}", ip, cursor1),
-                        cut_top(cut_top(draw_region, height).1, height).0,
-                    ),
-                    (
-                        format!("port: {:?}{}", port, cursor2),
-                        cut_bottom(cut_bottom(draw_region, height).0, height).1,
-                    ),
-                    ("Start".to_owned(), cut_bottom(draw_region, height).1),
-                ] {
+                let rules = if *host {
+                    let rules = &self.rule_sets[*rules];
+                    format!("rules: {} ({})", rules.name, rules.mode().label())
+                } else {
+                    "rules: chosen by host".to_owned()
+                };
+                for (i, text) in [
+                    if *tournament {
+                        format!("joining tournament as {}", self.player_name)
+                    } else {
+                        format!("host: {:?}", host)
+                    },
+                    format!("ip: {:}{}", ip, cursor1),
+                    format!("port: {:?}{}", port, cursor2),
+                    rules,
+                    format!("handicap: {}", HANDICAPS[self.handicap].name),
+                    format!("words: {}", self.word_list_name),
+                    "Start".to_owned(),
+                ].into_iter().enumerate() {
                     center_text_in_rect(
                         ctx,
                         &mut canvas,
                         &Text::new(
-                            TextFragment::new(i.0)
+                            TextFragment::new(text)
                                 .color(TEXT_COLOR)
                                 .scale(50.0)
                                 .font("courier_new"),
                         ),
-                        i.1,
+                        cut_top(cut_top(draw_region, height * i as f32).1, height).0,
                     )
                 }
             },
 
+            GameState::StartMenu { selected } => {
+                let height = draw_region.h / MENU_OPTIONS.len() as f32;
+
+                for (i, option) in MENU_OPTIONS.iter().enumerate() {
+                    let row = cut_top(cut_top(draw_region, height * i as f32).1, height).0;
+                    let label = match option {
+                        MenuOption::WordList => format!("{}: {}", option.label(), self.word_list_name),
+                        option => option.label()
+                    };
+                    let label = if i == *selected {
+                        format!("> {} <", label)
+                    } else {
+                        label
+                    };
+
+                    center_text_in_rect(
+                        ctx,
+                        &mut canvas,
+                        &Text::new(
+                            TextFragment::new(label)
+                                .color(if i == *selected { TEXT_COLOR } else { LIGHT_TEXT_COLOR })
+                                .scale(70.0)
+                                .font("courier_new"),
+                        ),
+                        row,
+                    );
+                }
+            },
+            GameState::PracticeReport { duration, words, wpm, accuracy, mistakes } => {
+                center_text_in_rect(
+                    ctx,
+                    &mut canvas,
+                    &Text::new(
+                        TextFragment::new(format!("{:.1}wpm", wpm))
+                            .color(Color::BLACK)
+                            .scale(150.0)
+                            .font("courier_new"),
+                    ),
+                    draw_region,
+                );
+
+                center_text_in_rect(
+                    ctx,
+                    &mut canvas,
+                    &Text::new(
+                        TextFragment::new(format!(
+                            "{} words from {} in {}s\n{:.1}% accuracy, {} mistakes\npress r to practice again, m for menu",
+                            words,
+                            self.word_list_name,
+                            duration,
+                            accuracy * 100.0,
+                            mistakes
+                        ))
+                        .color(TEXT_COLOR)
+                        .scale(50.0)
+                        .font("courier_new"),
+                    ),
+                    cut_top(draw_region, draw_region.h / 2.0).1,
+                );
+            },
+
+            GameState::SurvivalReport { score, wpm, best, place } => {
+                let (title_region, table_region) = cut_top(draw_region, draw_region.h / 3.0);
+                let survived = score.time as u64;
+
+                center_text_in_rect(
+                    ctx,
+                    &mut canvas,
+                    &Text::new(
+                        TextFragment::new(format!("{}:{:02}, wave {}", survived / 60, survived % 60, score.wave))
+                            .color(Color::BLACK)
+                            .scale(120.0)
+                            .font("courier_new"),
+                    ),
+                    title_region,
+                );
+
+                let mut table = match place {
+                    Some(0) => "new best!\n".to_owned(),
+                    Some(place) => format!("#{} best\n", place + 1),
+                    None => String::new()
+                };
+                table += &format!("{} words, {:.1}wpm\n\n", score.words, wpm);
+
+                for (i, run) in best.iter().enumerate() {
+                    let secs = run.time as u64;
+                    table += &format!(
+                        "{}{}. {}:{:02} wave {} ({} words)\n",
+                        if Some(i) == *place { "> " } else { "" },
+                        i + 1,
+                        secs / 60,
+                        secs % 60,
+                        run.wave,
+                        run.words
+                    );
+                }
+                table += "press r to try again, m for menu";
+
+                center_text_in_rect(
+                    ctx,
+                    &mut canvas,
+                    &Text::new(
+                        TextFragment::new(table)
+                            .color(TEXT_COLOR)
+                            .scale(35.0)
+                            .font("courier_new"),
+                    ),
+                    table_region,
+                );
+            },
+
+            GameState::HostingTournament { host, .. } => {
+                let status = match (&host.tournament, host.break_left()) {
+                    (None, _) => format!(
+                        "hosting a tournament on port {}\nformat: {} (f to change)\nrules: {} (r to change)\n{}",
+                        TOURNAMENT_PORT,
+                        host.format.label(),
+                        host.rules.name,
+                        if host.can_start() {
+                            "press enter to start".to_owned()
+                        } else {
+                            format!("needs at least {} players to start", MIN_PLAYERS)
+                        }
+                    ),
+                    (Some(tournament), _) if tournament.winner().is_some() => "the tournament is over".to_owned(),
+                    (Some(_), Some(left)) => format!("next round in {}s", left.ceil() as u64),
+                    (Some(_), None) => "round in progress".to_owned()
+                };
+
+                render_bracket(ctx, &mut canvas, &format!("{}\n\n{}\nm to stop hosting", host.bracket_text(), status), draw_region);
+            },
+            GameState::Connecting { .. } => {
+                render_bracket(ctx, &mut canvas, "setting up the match...\n\nm to cancel", draw_region);
+            },
+            GameState::TournamentLobby { bracket, handshake, .. } => {
+                let status = if handshake.is_some() { "setting up your match...\n" } else { "" };
+                render_bracket(ctx, &mut canvas, &format!("{}\n\n{}m to leave the tournament", bracket, status), draw_region);
+            },
+            GameState::DailyReport { result, wpm, best } => {
+                let (title_region, info_region) = cut_top(draw_region, draw_region.h / 3.0);
+                let survived = result.score.time as u64;
+                let best_secs = best.score.time as u64;
+
+                center_text_in_rect(
+                    ctx,
+                    &mut canvas,
+                    &Text::new(
+                        TextFragment::new(format!("{}:{:02}, wave {}", survived / 60, survived % 60, result.score.wave))
+                            .color(Color::BLACK)
+                            .scale(120.0)
+                            .font("courier_new"),
+                    ),
+                    title_region,
+                );
+
+                center_text_in_rect(
+                    ctx,
+                    &mut canvas,
+                    &Text::new(
+                        TextFragment::new(format!(
+                            "daily {}\n{} words, {:.1}wpm\n{}\n\nshare your score:\n{}\n\npress r to try again, m for menu",
+                            daily::date(result.day),
+                            result.score.words,
+                            wpm,
+                            if best == result {
+                                "your best today!".to_owned()
+                            } else {
+                                format!("your best today: {}:{:02}, wave {}", best_secs / 60, best_secs % 60, best.score.wave)
+                            },
+                            result.export()
+                        ))
+                        .color(TEXT_COLOR)
+                        .scale(35.0)
+                        .font("courier_new"),
+                    ),
+                    info_region,
+                );
+            },
+
             GameState::InvalidState => panic!("Leaked InvalidState!")
         }
         canvas.finish(ctx)
@@ -268,21 +687,50 @@ impl EventHandler for WordGame {
             GameState::ConnectionConfig {
                 ref mut input_y,
                 ref mut host,
+                ref mut rules,
+                tournament,
                 ..
             } => {
-                let mut new_input_y = (y * 4.0 / shrink(self.draw_rect, -MARGIN).h).floor() as u32;
+                let mut new_input_y = (y * 7.0 / shrink(self.draw_rect, -MARGIN).h).floor() as u32;
 
                 if new_input_y == 0 {
-                    *host = !*host;
+                    // tournament players never host
+                    *host = !*host && !tournament;
+                    new_input_y = *input_y
+                }
+
+                if new_input_y == 3 {
+                    *rules = (*rules + 1) % self.rule_sets.len();
+                    new_input_y = *input_y
+                }
+
+                if new_input_y == 4 {
+                    self.handicap = (self.handicap + 1) % HANDICAPS.len();
+                    new_input_y = *input_y
+                }
+
+                let cycle_words = new_input_y == 5;
+                if cycle_words {
                     new_input_y = *input_y
                 }
 
                 *input_y = new_input_y;
 
-                if new_input_y == 3 {
+                if cycle_words {
+                    self.next_word_list();
+                }
+
+                if new_input_y == 6 {
                     self.pair_up_ui();
                 }
-            }
+            },
+            GameState::StartMenu { .. } => {
+                let selected = (y * MENU_OPTIONS.len() as f32 / shrink(self.draw_rect, -MARGIN).h).floor() as usize;
+
+                if let Some(option) = MENU_OPTIONS.get(selected) {
+                    self.select_menu_option(*option);
+                }
+            },
             _ => {}
         }
         Ok(())
@@ -295,20 +743,76 @@ impl EventHandler for WordGame {
     ) -> Result<(), ggez::GameError> {
         match self.state {
             GameState::Ongoing(ref mut ongoing) => {
-                if character.is_alphabetic() || character == ' ' {
-                    ongoing.current_text.push(character);
+                if !ongoing.can_type() {
+                    return Ok(());
+                }
+
+                if ongoing.rules.text.allows(character) {
+                    ongoing.type_char(character);
                 }
             }
-            GameState::Ended { ref mut waiting_to_restart, ref mut conn, .. } => match character {
+            GameState::Ended { ref mut waiting_to_restart, ref mut opponent_waiting_to_restart, ref mut conn, .. } => match character {
                 'r' | 'R' => {
                     *waiting_to_restart = true;
-                    conn.send_packet(Packet::WaitingToRestart)?;
+                    match conn {
+                        Some(conn) => conn.send_packet(Packet::WaitingToRestart)?,
+                        // there is nobody to wait for
+                        None => *opponent_waiting_to_restart = true
+                    }
                 }
                 'n' | 'N' => {
-                    self.state = GameState::ConnectionConfig { input_y: 1, host: false, ip: "localhost".to_owned(), port: 5555}
+                    self.state = GameState::ConnectionConfig { input_y: 1, host: false, ip: "localhost".to_owned(), port: 5555, rules: 0, tournament: false }
+                }
+                'm' | 'M' => {
+                    self.state = GameState::StartMenu { selected: 0 }
+                }
+                _ => {}
+            },
+            GameState::PracticeReport { duration, .. } => match character {
+                'r' | 'R' => {
+                    self.state = GameState::Ongoing(self.practice_game(duration))
+                }
+                'm' | 'M' => {
+                    self.state = GameState::StartMenu { selected: 0 }
+                }
+                _ => {}
+            },
+            GameState::SurvivalReport { .. } => match character {
+                'r' | 'R' => {
+                    self.state = GameState::Ongoing(self.survival_game())
+                }
+                'm' | 'M' => {
+                    self.state = GameState::StartMenu { selected: 0 }
+                }
+                _ => {}
+            },
+            GameState::DailyReport { .. } => match character {
+                'r' | 'R' => if let Some(daily) = self.daily_game() {
+                    self.state = GameState::Ongoing(daily)
+                },
+                'm' | 'M' => {
+                    self.state = GameState::StartMenu { selected: 0 }
                 }
                 _ => {}
             },
+            GameState::HostingTournament { ref mut host, ref mut rules } => match character {
+                'f' | 'F' if host.tournament.is_none() => {
+                    let next = Format::ALL.iter().position(|f| *f == host.format).map_or(0, |i| i + 1);
+                    host.format = Format::ALL[next % Format::ALL.len()];
+                }
+                'r' | 'R' if host.tournament.is_none() => {
+                    *rules = (*rules + 1) % self.rule_sets.len();
+                    host.rules = self.rule_sets[*rules].clone();
+                }
+                'm' | 'M' => {
+                    self.state = GameState::StartMenu { selected: 0 }
+                }
+                _ => {}
+            },
+            GameState::Connecting { .. } | GameState::TournamentLobby { .. } => if let 'm' | 'M' = character {
+                self.state = GameState::StartMenu { selected: 0 }
+            },
+            GameState::StartMenu { .. } => {},
             GameState::ConnectionConfig {
                 ref mut input_y,
                 ref mut ip,
@@ -328,7 +832,7 @@ impl EventHandler for WordGame {
                         }
                     }
                 }
-                3 => {
+                6 => {
                     if character == '\n' {
                         self.pair_up_ui();
                     }
@@ -360,7 +864,7 @@ impl EventHandler for WordGame {
         match input.keycode {
             Some(VirtualKeyCode::Back) => match self.state {
                 GameState::Ongoing(ref mut ongoing) => {
-                    ongoing.current_text.pop();
+                    text::pop_grapheme(&mut ongoing.current_text);
                 },
                 GameState::ConnectionConfig {
                     input_y,
@@ -378,43 +882,26 @@ impl EventHandler for WordGame {
                 },
                 _ => {}
             },
+            Some(VirtualKeyCode::Up) => if let GameState::StartMenu { ref mut selected } = self.state {
+                *selected = (*selected + MENU_OPTIONS.len() - 1) % MENU_OPTIONS.len();
+            },
+            Some(VirtualKeyCode::Down) => if let GameState::StartMenu { ref mut selected } = self.state {
+                *selected = (*selected + 1) % MENU_OPTIONS.len();
+            },
+            Some(VirtualKeyCode::Escape) => if let GameState::ConnectionConfig { .. } = self.state {
+                self.state = GameState::StartMenu { selected: 0 };
+            },
             Some(VirtualKeyCode::Return) => {
-                if let GameState::Ongoing(OngoingGame{
-                    ref mut current_words,
-                    ref mut received_words,
-                    ref mut current_text,
-
-                    ref mut total_words,
-
-                    ref mut conn,
-                    
-                    ..
-                }) = self.state {
-                    let lower = current_text.to_lowercase();
-                    let lower = &lower;
-
-                    let mut words_to_send = HashSet::new();
-
-                    for word in current_words.iter() {
-                        if word.to_lowercase() == *lower {
-                            words_to_send.insert(word.clone());
-                        }
-                    }
-
-                    let start_len = current_words.len() + received_words.len();
-
-                    current_words.retain(move |w| w.to_lowercase() != *lower);
-                    received_words.retain(move |w| w.to_lowercase() != *lower);
-
-                    let len_change = start_len - (current_words.len() + received_words.len());
-
-                    *total_words += len_change as u64;
-                    for word in words_to_send.iter() {
-                        debug!("Sending '{}'", word);
-                        conn.send_packet(Packet::add_word(&word))?;
-                    }
-
-                    current_text.clear();
+                if let GameState::Ongoing(ref mut ongoing) = self.state {
+                    let list = match ongoing.mode {
+                        GameMode::Daily { .. } => &self.daily_word_list,
+                        _ => &self.word_list
+                    };
+                    ongoing.submit(list)?;
+                } else if let GameState::StartMenu { selected } = self.state {
+                    self.select_menu_option(MENU_OPTIONS[selected]);
+                } else if let GameState::HostingTournament { ref mut host, .. } = self.state {
+                    host.start()?;
                 }
             }
             _ => {}
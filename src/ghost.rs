@@ -0,0 +1,155 @@
+use std::{fmt, fs};
+use std::io;
+use std::path::Path;
+
+use crate::user_data;
+use crate::word_game::GameOutcome;
+
+pub const LAST_GHOST: &str = "ghosts/last.txt";
+pub const BEST_GHOST: &str = "ghosts/best.txt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GhostEventKind {
+    Cleared, Sent
+}
+
+#[derive(Debug, Clone)]
+pub struct GhostEvent {
+    /// seconds since the start of the match
+    pub time: f32,
+    pub kind: GhostEventKind,
+    pub word: String
+}
+
+/// a recording of every word cleared and sent during one match
+#[derive(Debug, Clone, Default)]
+pub struct GhostRun {
+    pub events: Vec<GhostEvent>,
+    pub length: f32,
+    pub outcome: Option<GameOutcome>
+}
+
+impl GhostRun {
+    pub fn record(&mut self, time: f32, kind: GhostEventKind, word: &str) {
+        self.events.push(GhostEvent { time, kind, word: word.to_owned() });
+    }
+
+    pub fn finish(&mut self, length: f32, outcome: GameOutcome) {
+        self.length = length;
+        self.outcome = Some(outcome);
+    }
+
+    pub fn words_cleared(&self) -> usize {
+        self.events.iter().filter(|e| e.kind == GhostEventKind::Cleared).count()
+    }
+
+    pub fn wpm(&self) -> f32 {
+        self.words_cleared() as f32 * 60.0 / self.length.max(1.0)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid ghost line '{line}'"));
+        let mut run = GhostRun::default();
+
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let mut parts = line.splitn(3, ' ');
+            let (first, second, rest) = (parts.next(), parts.next(), parts.next());
+
+            match (first, second, rest) {
+                (Some("length"), Some(length), None) => {
+                    run.length = length.parse().map_err(|_| invalid(line))?;
+                },
+                (Some("outcome"), Some("win"), None) => run.outcome = Some(GameOutcome::Win),
+                (Some("outcome"), Some("loss"), None) => run.outcome = Some(GameOutcome::Loss),
+                (Some("outcome"), Some("draw"), None) => run.outcome = Some(GameOutcome::Draw),
+                (Some(time), Some(kind), Some(word)) => {
+                    let kind = match kind {
+                        "cleared" => GhostEventKind::Cleared,
+                        "sent" => GhostEventKind::Sent,
+                        _ => return Err(invalid(line))
+                    };
+
+                    run.events.push(GhostEvent {
+                        time: time.parse().map_err(|_| invalid(line))?,
+                        kind,
+                        word: word.to_owned()
+                    });
+                },
+                _ => return Err(invalid(line))
+            }
+        }
+
+        run.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Ok(run)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, self.to_string())
+    }
+
+    /// saves the run as the latest run, and as the best run if it beats the previous best, in the
+    /// [`crate::user_data`] folder
+    pub fn save_run(&self) -> io::Result<()> {
+        self.save(user_data::path(LAST_GHOST))?;
+
+        let is_best = match Self::load(user_data::path(BEST_GHOST)) {
+            Ok(best) => self.wpm() > best.wpm(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => true,
+            Err(e) => {
+                warn!("Replacing unreadable best ghost: {}", e);
+                true
+            }
+        };
+
+        if is_best {
+            info!("New best run! {:.1}wpm", self.wpm());
+            self.save(user_data::path(BEST_GHOST))?;
+        }
+
+        Ok(())
+    }
+
+    /// the run the ghost opponent should replay: the best run, or else the latest one
+    pub fn load_opponent() -> io::Result<Self> {
+        Self::load(user_data::path(BEST_GHOST)).or_else(|_| Self::load(user_data::path(LAST_GHOST)))
+    }
+}
+
+/// Format:
+/// ```text
+/// length <secs>
+/// outcome <win|loss|draw>
+/// <secs> <cleared|sent> <word>
+/// ...
+/// ```
+impl fmt::Display for GhostRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "length {}", self.length)?;
+
+        match self.outcome {
+            Some(GameOutcome::Win) => writeln!(f, "outcome win")?,
+            Some(GameOutcome::Loss) => writeln!(f, "outcome loss")?,
+            Some(GameOutcome::Draw) => writeln!(f, "outcome draw")?,
+            None => {}
+        }
+
+        for event in self.events.iter() {
+            let kind = match event.kind {
+                GhostEventKind::Cleared => "cleared",
+                GhostEventKind::Sent => "sent"
+            };
+            writeln!(f, "{} {} {}", event.time, kind, event.word)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+//! Setting up a match without blocking the game. Both players send their version first, then the host sends the
+//! match settings, its handicap and the hash of its word list, and the other player answers with its handicap,
+//! asking for the host's list first if it doesn't have an identical one. Both sides are polled every frame until
+//! they agree.
+
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::network::{check_version, Connection, Packet};
+use crate::rules::{GameRules, Handicap};
+use crate::word_game::load_word_list_file;
+use crate::word_list::WordList;
+
+/// how long to wait for the next packet before giving up
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// a word list with its name and the file it's in, which is empty for downloaded lists
+pub type NamedWordList = (String, WordList, PathBuf);
+
+/// what both players agreed on
+#[derive(Debug)]
+pub struct Agreement {
+    pub rules: GameRules,
+    /// yours first
+    pub handicaps: (Handicap, Handicap),
+    /// the host's word list, if it isn't the one you play with already
+    pub word_list: Option<NamedWordList>
+}
+
+#[derive(Debug)]
+pub struct Handshake {
+    host: bool,
+    handicap: Handicap,
+    /// whether the other player's version matches
+    version_checked: bool,
+    rules: Option<GameRules>,
+    opponent_handicap: Option<Handicap>,
+    /// the host's word list once it's known, None inside if it's the current one
+    word_list: Option<Option<NamedWordList>>,
+    /// the hash of the list asked from the host
+    requested: Option<u64>,
+    last_packet: Instant
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Handshake {
+    fn new(host: bool, handicap: Handicap) -> Self {
+        Handshake {
+            host,
+            handicap,
+            version_checked: false,
+            rules: None,
+            opponent_handicap: None,
+            word_list: None,
+            requested: None,
+            last_packet: Instant::now()
+        }
+    }
+
+    /// starts a match with `rules` and the word list `word_list`
+    pub fn host(conn: &mut Connection, rules: GameRules, handicap: Handicap, word_list: (&str, &WordList)) -> io::Result<Self> {
+        conn.send_packet(Packet::client_info())?;
+        conn.send_packet(Packet::MatchSettings { rules: rules.clone() })?;
+        conn.send_packet(Packet::Handicap { handicap })?;
+        conn.send_packet(Packet::WordListHash { name: word_list.0.to_owned(), hash: word_list.1.hash() })?;
+
+        Ok(Handshake { rules: Some(rules), word_list: Some(None), ..Self::new(true, handicap) })
+    }
+
+    /// waits for the host's match settings
+    pub fn join(conn: &mut Connection, handicap: Handicap) -> io::Result<Self> {
+        conn.send_packet(Packet::client_info())?;
+        Ok(Self::new(false, handicap))
+    }
+
+    /// waits for the settings of a tournament match. versions were checked when registering
+    pub fn tournament_match(handicap: Handicap) -> Self {
+        Handshake { version_checked: true, ..Self::new(false, handicap) }
+    }
+
+    /// handles the packets that have arrived. `word_list` is the one you play with
+    pub fn poll(&mut self, conn: &mut Connection, word_list: (&str, &WordList)) -> io::Result<Option<Agreement>> {
+        while let Some(packet) = conn.poll_next_packet()? {
+            if let Some(agreement) = self.handle(conn, packet, word_list)? {
+                return Ok(Some(agreement));
+            }
+        }
+
+        self.check_timeout()?;
+        Ok(None)
+    }
+
+    pub fn check_timeout(&self) -> io::Result<()> {
+        if self.last_packet.elapsed() > TIMEOUT {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Timed out setting up the match"));
+        }
+
+        Ok(())
+    }
+
+    /// handles one packet of the handshake, returning what was agreed once it's over
+    pub fn handle(&mut self, conn: &mut Connection, packet: Packet, word_list: (&str, &WordList)) -> io::Result<Option<Agreement>> {
+        self.last_packet = Instant::now();
+
+        match packet {
+            Packet::ClientInfo { major, minor } if !self.version_checked => {
+                check_version(major, minor)?;
+                self.version_checked = true;
+            },
+            packet if !self.version_checked => return Err(invalid(format!("Expected client info, received {:?}", packet))),
+            Packet::MatchSettings { rules } if !self.host => self.rules = Some(rules),
+            Packet::Handicap { handicap } => self.opponent_handicap = Some(handicap),
+            Packet::WordListHash { name, hash } if !self.host => {
+                self.word_list = Self::find_word_list(&name, hash, word_list.1);
+                if self.word_list.is_none() {
+                    info!("Downloading word list {} from the host", name);
+                    conn.send_packet(Packet::WordListRequest)?;
+                    self.requested = Some(hash);
+                }
+            },
+            Packet::WordList { name, list } if self.requested.is_some() && !list.words.is_empty() => {
+                if Some(list.hash()) != self.requested {
+                    warn!("Word list {} from the host doesn't match its hash", name);
+                }
+                self.word_list = Some(Some((name, list, PathBuf::new())));
+            },
+            Packet::WordListRequest if self.host => conn.send_packet(Packet::WordList {
+                name: word_list.0.to_owned(),
+                list: word_list.1.clone()
+            })?,
+            packet => return Err(invalid(format!("Unexpected packet {:?} while setting up the match", packet)))
+        }
+
+        let (Some(rules), Some(opponent_handicap), Some(_)) = (&self.rules, self.opponent_handicap, &self.word_list) else {
+            return Ok(None);
+        };
+        if !self.host {
+            conn.send_packet(Packet::Handicap { handicap: self.handicap })?;
+        }
+
+        Ok(Some(Agreement {
+            rules: rules.clone(),
+            handicaps: (self.handicap, opponent_handicap),
+            word_list: self.word_list.take().flatten()
+        }))
+    }
+
+    /// the host's list if it can be played without downloading it: the current list or a local one of the same
+    /// name, if they are identical to it. None if it has to be downloaded
+    fn find_word_list(name: &str, hash: u64, current: &WordList) -> Option<Option<NamedWordList>> {
+        if current.hash() == hash {
+            return Some(None);
+        }
+
+        let path = WordList::find(name).ok()?;
+        let list = load_word_list_file(&path).ok()?;
+        (list.hash() == hash).then(|| Some((name.to_owned(), list, path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::BotMatch;
+    use crate::rules::HANDICAPS;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    /// both ends of a connection
+    fn pair() -> (Connection, Connection) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let other = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        (Connection::new(stream).unwrap(), Connection::new(other).unwrap())
+    }
+
+    /// polls until the handshake fails
+    fn poll_error(handshake: &mut Handshake, conn: &mut Connection) -> io::Error {
+        let list = WordList::parse_txt("apple");
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            match handshake.poll(conn, ("list", &list)) {
+                Ok(None) => thread::sleep(Duration::from_millis(5)),
+                Ok(Some(agreement)) => panic!("Agreed on {:?}", agreement),
+                Err(e) => return e
+            }
+        }
+
+        panic!("The handshake never failed");
+    }
+
+    #[test]
+    fn downloads_a_word_list_it_doesnt_have() {
+        let (mut host_conn, mut join_conn) = pair();
+        let hosted = WordList::parse_txt("apple\nbanana\ncherry");
+        let own = WordList::parse_txt("dog\ncat");
+        let handicap = HANDICAPS[1];
+
+        let mut host = Handshake::host(&mut host_conn, GameRules::default(), handicap, ("not_a_local_list", &hosted)).unwrap();
+        let mut join = Handshake::join(&mut join_conn, Handicap::default()).unwrap();
+
+        let (mut hosted_agreement, mut joined_agreement) = (None, None);
+        let start = Instant::now();
+        while hosted_agreement.is_none() || joined_agreement.is_none() {
+            assert!(start.elapsed() < Duration::from_secs(5), "The handshake never finished");
+            if hosted_agreement.is_none() {
+                hosted_agreement = host.poll(&mut host_conn, ("not_a_local_list", &hosted)).unwrap();
+            }
+            if joined_agreement.is_none() {
+                joined_agreement = join.poll(&mut join_conn, ("own", &own)).unwrap();
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let (hosted_agreement, joined_agreement) = (hosted_agreement.unwrap(), joined_agreement.unwrap());
+        assert!(hosted_agreement.word_list.is_none());
+        assert_eq!(hosted_agreement.handicaps.1, Handicap::default());
+
+        let (name, list, path) = joined_agreement.word_list.unwrap();
+        assert_eq!((name.as_str(), list.hash(), path), ("not_a_local_list", hosted.hash(), PathBuf::new()));
+        assert_eq!(joined_agreement.handicaps, (Handicap::default(), handicap));
+        assert_eq!(joined_agreement.rules.name, GameRules::default().name);
+    }
+
+    #[test]
+    fn local_bots_answer_the_handshake() {
+        let (mut host_conn, mut bot_conn) = pair();
+        let bot = thread::spawn(move || {
+            let mut bot_match = BotMatch::new();
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_secs(1) {
+                if let Some(packet) = bot_conn.poll_next_packet().unwrap() {
+                    bot_match.handle_packet(&mut bot_conn, &packet).unwrap();
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+
+        let list = WordList::parse_txt("apple");
+        let mut host = Handshake::host(&mut host_conn, GameRules::default(), HANDICAPS[1], ("list", &list)).unwrap();
+        let start = Instant::now();
+        let agreement = loop {
+            assert!(start.elapsed() < Duration::from_secs(1), "The bot never answered");
+            if let Some(agreement) = host.poll(&mut host_conn, ("list", &list)).unwrap() {
+                break agreement;
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+
+        assert_eq!(agreement.handicaps, (HANDICAPS[1], Handicap::default()));
+        bot.join().unwrap();
+    }
+
+    #[test]
+    fn rejects_packets_out_of_turn() {
+        let (mut host_conn, mut join_conn) = pair();
+        let list = WordList::parse_txt("apple");
+        let mut host = Handshake::host(&mut host_conn, GameRules::default(), Handicap::default(), ("list", &list)).unwrap();
+
+        join_conn.send_packet(Packet::MatchSettings { rules: GameRules::default() }).unwrap();
+        assert_eq!(poll_error(&mut host, &mut host_conn).kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let (mut host_conn, mut join_conn) = pair();
+        let list = WordList::parse_txt("apple");
+        let mut host = Handshake::host(&mut host_conn, GameRules::default(), Handicap::default(), ("list", &list)).unwrap();
+
+        join_conn.send_packet(Packet::ClientInfo { major: u16::MAX, minor: 0 }).unwrap();
+        assert!(poll_error(&mut host, &mut host_conn).to_string().contains("mismatched version"));
+    }
+}
@@ -0,0 +1,229 @@
+//! Keystroke statistics kept across sessions in `scores/keystrokes.txt` in the [`crate::user_data`] folder: how long each letter, bigram and finger
+//! transition takes to type and how often it's missed. Games with `adaptive` rules use them to put the words
+//! you're worst at on the board more often.
+
+use std::collections::BTreeMap;
+use std::{fmt, io};
+
+use crate::user_data;
+
+pub const KEYSTROKES: &str = "scores/keystrokes.txt";
+/// longer pauses between two keys aren't counted as the time it took to type the second one
+pub const MAX_KEY_SECS: f32 = 2.0;
+/// keys typed fewer times than this are assumed to be average
+const MIN_SAMPLES: u64 = 5;
+/// how many seconds a miss counts as when comparing keys
+const MISS_SECS: f32 = 1.0;
+/// how strongly adaptive selection favors weak words, as a power of how much slower than average they are
+const ADAPTIVE_STRENGTH: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Letter,
+    Bigram,
+    /// from the finger typing a letter to the one typing the next, on a qwerty keyboard
+    Transition
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Letter => "letter",
+            Kind::Bigram => "bigram",
+            Kind::Transition => "transition"
+        }
+    }
+}
+
+/// the finger a letter is typed with on a qwerty keyboard, as l or r for the hand and 1 to 4 from the index
+fn finger(c: char) -> Option<&'static str> {
+    Some(match c {
+        'q' | 'a' | 'z' => "l4",
+        'w' | 's' | 'x' => "l3",
+        'e' | 'd' | 'c' => "l2",
+        'r' | 'f' | 'v' | 't' | 'g' | 'b' => "l1",
+        'y' | 'h' | 'n' | 'u' | 'j' | 'm' => "r1",
+        'i' | 'k' => "r2",
+        'o' | 'l' => "r3",
+        'p' => "r4",
+        _ => return None
+    })
+}
+
+/// what typing `c` after `prev` exercises
+fn features(prev: Option<char>, c: char) -> Vec<(Kind, String)> {
+    let mut features = vec![(Kind::Letter, c.to_string())];
+
+    if let Some(prev) = prev {
+        features.push((Kind::Bigram, format!("{prev}{c}")));
+
+        if let (Some(from), Some(to)) = (finger(prev), finger(c)) {
+            features.push((Kind::Transition, format!("{from}-{to}")));
+        }
+    }
+
+    features
+}
+
+/// the letters of `word` the way they are recorded
+fn letters(word: &str) -> Vec<char> {
+    word.chars().flat_map(char::to_lowercase).filter(|c| c.is_alphabetic()).collect()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KeyStat {
+    /// times typed correctly
+    pub hits: u64,
+    pub misses: u64,
+    /// how many of the hits were timed, and the seconds they took in total
+    pub timed: u64,
+    pub secs: f32
+}
+
+impl KeyStat {
+    fn add(&mut self, other: &KeyStat) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.timed += other.timed;
+        self.secs += other.secs;
+    }
+
+    /// the average seconds it takes to type, counting misses as [`MISS_SECS`]. None without enough samples
+    pub fn cost(&self) -> Option<f32> {
+        if self.timed < MIN_SAMPLES || self.hits + self.misses < MIN_SAMPLES {
+            return None;
+        }
+
+        let miss_rate = self.misses as f32 / (self.hits + self.misses) as f32;
+        Some(self.secs / self.timed as f32 + miss_rate * MISS_SECS)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyStats {
+    pub keys: BTreeMap<(Kind, String), KeyStat>
+}
+
+impl KeyStats {
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// records `c` typed after `prev`, taking `secs` if it was timed. misses are recorded for the letter that
+    /// should have been typed
+    pub fn keystroke(&mut self, prev: Option<char>, c: char, secs: Option<f32>, hit: bool) {
+        let lower = |c: char| c.to_lowercase().next().unwrap_or(c);
+        let prev = prev.filter(|p| p.is_alphabetic()).map(lower);
+        if !c.is_alphabetic() {
+            return;
+        }
+
+        for feature in features(prev, lower(c)) {
+            let stat = self.keys.entry(feature).or_default();
+
+            if !hit {
+                stat.misses += 1;
+                continue;
+            }
+
+            stat.hits += 1;
+            if let Some(secs) = secs {
+                stat.timed += 1;
+                stat.secs += secs;
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &KeyStats) {
+        for (feature, stat) in other.keys.iter() {
+            self.keys.entry(feature.clone()).or_default().add(stat);
+        }
+    }
+
+    /// the average cost of the letters typed so far, None until there are enough stats to pick words with
+    pub fn average_cost(&self) -> Option<f32> {
+        let costs: Vec<f32> = self.keys.iter()
+            .filter(|((kind, _), _)| *kind == Kind::Letter)
+            .filter_map(|(_, stat)| stat.cost())
+            .collect();
+
+        (!costs.is_empty()).then(|| costs.iter().sum::<f32>() / costs.len() as f32)
+    }
+
+    /// how much more often `word` should come up than an average word: above 1 for words made of keys that
+    /// are slower or missed more than average, below 1 for easier ones
+    pub fn weight(&self, word: &str, average_cost: f32) -> f32 {
+        let letters = letters(word);
+
+        let costs: Vec<f32> = letters.iter().enumerate()
+            .flat_map(|(i, c)| features(i.checked_sub(1).map(|p| letters[p]), *c))
+            .map(|feature| self.keys.get(&feature).and_then(KeyStat::cost).unwrap_or(average_cost))
+            .collect();
+
+        if costs.is_empty() {
+            return 1.0;
+        }
+
+        let cost = costs.iter().sum::<f32>() / costs.len() as f32;
+        (cost / average_cost).powf(ADAPTIVE_STRENGTH)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut stats = KeyStats::default();
+
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Invalid keystroke stat '{line}'"));
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let [kind, key, hits, misses, timed, secs] = parts[..] else {
+                return Err(invalid());
+            };
+
+            let kind = match kind {
+                "letter" => Kind::Letter,
+                "bigram" => Kind::Bigram,
+                "transition" => Kind::Transition,
+                _ => return Err(invalid())
+            };
+
+            stats.keys.insert((kind, key.to_owned()), KeyStat {
+                hits: hits.parse().map_err(|_| invalid())?,
+                misses: misses.parse().map_err(|_| invalid())?,
+                timed: timed.parse().map_err(|_| invalid())?,
+                secs: secs.parse().map_err(|_| invalid())?
+            });
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Format: a `<kind> <key> <hits> <misses> <timed hits> <secs>` line per key, kind being letter, bigram or transition
+impl fmt::Display for KeyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ((kind, key), stat) in self.keys.iter() {
+            writeln!(f, "{} {} {} {} {} {}", kind.label(), key, stat.hits, stat.misses, stat.timed, stat.secs)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// the stats of every session so far. empty if nothing has been recorded yet
+pub fn load() -> io::Result<KeyStats> {
+    match user_data::read_to_string(KEYSTROKES) {
+        Ok(text) => KeyStats::parse(&text),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(KeyStats::default()),
+        Err(e) => Err(e)
+    }
+}
+
+/// adds the stats of a session to the saved ones
+pub fn record(session: &KeyStats) -> io::Result<()> {
+    let mut stats = load().unwrap_or_else(|e| {
+        warn!("Replacing unreadable keystroke stats: {}", e);
+        KeyStats::default()
+    });
+    stats.merge(session);
+
+    user_data::write(KEYSTROKES, stats.to_string())
+}
@@ -8,6 +8,20 @@ pub mod network;
 pub mod word_game;
 pub mod render;
 pub mod events;
+pub mod simulation;
+pub mod ghost;
+pub mod bot_host;
+pub mod rules;
+pub mod survival;
+pub mod daily;
+pub mod tournament;
+pub mod powerup;
+pub mod text;
+pub mod word_list;
+pub mod keystrokes;
+pub mod user_data;
+pub mod handshake;
+pub mod board;
 
 use log::LevelFilter;
 use word_game::*;
@@ -26,6 +40,31 @@ fn init_logger() {
 fn main() {
     init_logger();
 
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("simulate") {
+        if let Err(e) = simulation::run_cli(&args[2..]) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("words") {
+        if let Err(e) = word_list::run_cli(&args[2..]) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("daily") {
+        if let Err(e) = daily::run_cli(&args[2..]) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Make a Context.
     let (mut ctx, event_loop) = ContextBuilder::new("my_game", "Cool Game Author")
         .window_mode(
@@ -43,7 +82,35 @@ fn main() {
     // Usually, you should provide it with the Context object to
     // use when setting your game up.
 
-    let my_game = WordGame::new(&mut ctx, DEFAULT_WORD_LIST);
+    let mut my_game = WordGame::new(&mut ctx, DEFAULT_WORD_LIST);
+
+    if let Some(i) = args.iter().position(|a| a == "--bot-command") {
+        my_game.bot_command = args.get(i + 1).cloned();
+    }
+
+    if let Some(name) = args.iter().position(|a| a == "--words").and_then(|i| args.get(i + 1)) {
+        if let Err(e) = my_game.set_word_list(name) {
+            error!("Couldn't load word list {}: {}", name, e);
+        }
+    }
+
+    if let Some(name) = args.iter().position(|a| a == "--name").and_then(|i| args.get(i + 1)) {
+        my_game.player_name = name.clone();
+    }
+
+    if let Some(name) = args.iter().position(|a| a == "--handicap").and_then(|i| args.get(i + 1)) {
+        match rules::HANDICAPS.iter().position(|h| h.name == name) {
+            Some(i) => my_game.handicap = i,
+            None => error!("Unknown handicap {}", name)
+        }
+    }
+
+    if let Some(name) = args.iter().position(|a| a == "--rules").and_then(|i| args.get(i + 1)) {
+        match rules::GameRules::find(name) {
+            Ok(rules) => my_game.rule_sets.insert(0, rules),
+            Err(e) => error!("Couldn't load rules {}: {}", name, e)
+        }
+    }
 
     // Run!
     event::run(ctx, event_loop, my_game);
@@ -1,15 +1,19 @@
 use std::net::{TcpStream, TcpListener};
 use std::io::{self, Read, ErrorKind, Write};
-use std::{thread, fs};
+use std::thread;
 use std::time::{Duration, Instant};
 
 use rand::Rng;
 
-use crate::word_game::DEFAULT_WORD_LIST;
+use crate::ghost::{GhostRun, GhostEventKind};
+use crate::powerup::PowerUp;
+use crate::rules::{GameRules, Handicap};
+use crate::word_game::{GameMode, GameOutcome, Score};
+use crate::word_list::WordList;
 
 type VersionType = u16;
 const MAJOR_VERSION: VersionType = 0;
-const MINOR_VERSION: VersionType = 2;
+const MINOR_VERSION: VersionType = 10;
 
 trait FriendlyRead {
     fn read_u8(&mut self) -> io::Result<u8>;
@@ -177,14 +181,62 @@ pub enum Packet {
     },
 
     AddWord {
-        word: String
+        word: String,
+        /// how much the word counts toward the receiver's limit
+        weight: u32
     },
 
     ILost {
 
     },
 
-    WaitingToRestart
+    WaitingToRestart,
+
+    /// sent by the host right after connecting
+    MatchSettings {
+        rules: GameRules
+    },
+
+    /// the sender's score in time attack. `done` is set on the final score, once their time is up
+    Score {
+        score: Score,
+        done: bool
+    },
+
+    /// the sender cleared a power-up whose effect hits the receiver
+    PowerUp {
+        power_up: PowerUp
+    },
+
+    /// sent by both players after the match settings
+    Handicap {
+        handicap: Handicap
+    },
+
+    /// sent by a player joining a tournament, see [`crate::tournament`]
+    Register {
+        name: String,
+        handicap: Handicap
+    },
+
+    /// the state of the tournament, sent by its host to players between matches
+    Bracket {
+        text: String
+    },
+
+    /// the name and hash of the host's word list, sent after its handicap
+    WordListHash {
+        name: String,
+        hash: u64
+    },
+
+    WordListRequest,
+
+    /// the host's word list, sent in answer to [`Packet::WordListRequest`]
+    WordList {
+        name: String,
+        list: WordList
+    }
 }
 
 impl Packet {
@@ -198,7 +250,8 @@ impl Packet {
             },
 
             1 => Self::AddWord { 
-                word: data.read_string()?
+                word: data.read_string()?,
+                weight: data.read_u32()?
             },
 
             2 => Self::ILost {
@@ -207,6 +260,57 @@ impl Packet {
 
             3 => Self::WaitingToRestart,
 
+            4 => Self::MatchSettings {
+                rules: GameRules::parse(&data.read_string()?)?
+            },
+
+            5 => Self::Score {
+                score: Score {
+                    words: data.read_u32()?,
+                    chars: data.read_u32()?
+                },
+                done: data.read_u8()? != 0
+            },
+
+            6 => {
+                let id = data.read_u8()?;
+                Self::PowerUp {
+                    power_up: PowerUp::from_id(id).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Unrecognised power-up {}", id)))?
+                }
+            },
+
+            7 => {
+                let id = data.read_u8()?;
+                Self::Handicap {
+                    handicap: Handicap::from_id(id).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Unrecognised handicap {}", id)))?
+                }
+            },
+
+            8 => {
+                let name = data.read_string()?;
+                let id = data.read_u8()?;
+                Self::Register {
+                    name,
+                    handicap: Handicap::from_id(id).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Unrecognised handicap {}", id)))?
+                }
+            },
+
+            9 => Self::Bracket {
+                text: data.read_string()?
+            },
+
+            10 => Self::WordListHash {
+                name: data.read_string()?,
+                hash: data.read_u64()?
+            },
+
+            11 => Self::WordListRequest,
+
+            12 => Self::WordList {
+                name: data.read_string()?,
+                list: WordList::parse_toml(&data.read_string()?)?
+            },
+
             x => {
                 return Err(io::Error::new(io::ErrorKind::Other, format!("Unrecognised packet type {}", x)));
             }
@@ -220,7 +324,16 @@ impl Packet {
             Self::ClientInfo {..}  => 0,
             Self::AddWord {..}     => 1,
             Self::ILost {..}       => 2,
-            Self::WaitingToRestart => 3
+            Self::WaitingToRestart => 3,
+            Self::MatchSettings {..} => 4,
+            Self::Score {..}       => 5,
+            Self::PowerUp {..}     => 6,
+            Self::Handicap {..}    => 7,
+            Self::Register {..}    => 8,
+            Self::Bracket {..}     => 9,
+            Self::WordListHash {..} => 10,
+            Self::WordListRequest  => 11,
+            Self::WordList {..}    => 12
         }
     }
 
@@ -233,15 +346,55 @@ impl Packet {
                 out.write_u16(*minor)?;
             },
 
-            Self::AddWord { word } => {
+            Self::AddWord { word, weight } => {
                 out.write_string(word)?;
+                out.write_u32(*weight)?;
             },
 
             Self::ILost { } => {
 
             },
 
-            Self::WaitingToRestart => {}
+            Self::WaitingToRestart => {},
+
+            Self::MatchSettings { rules } => {
+                out.write_string(&rules.to_toml()?)?;
+            },
+
+            Self::Score { score, done } => {
+                out.write_u32(score.words)?;
+                out.write_u32(score.chars)?;
+                out.write_u8(*done as u8)?;
+            },
+
+            Self::PowerUp { power_up } => {
+                out.write_u8(power_up.id())?;
+            },
+
+            Self::Handicap { handicap } => {
+                out.write_u8(handicap.id())?;
+            },
+
+            Self::Register { name, handicap } => {
+                out.write_string(name)?;
+                out.write_u8(handicap.id())?;
+            },
+
+            Self::Bracket { text } => {
+                out.write_string(text)?;
+            },
+
+            Self::WordListHash { name, hash } => {
+                out.write_string(name)?;
+                out.write_u64(*hash)?;
+            },
+
+            Self::WordListRequest => {},
+
+            Self::WordList { name, list } => {
+                out.write_string(name)?;
+                out.write_string(&list.to_toml()?)?;
+            }
         }
 
         Ok(())
@@ -251,8 +404,8 @@ impl Packet {
         Self::ClientInfo { major: MAJOR_VERSION, minor: MINOR_VERSION }
     }
 
-    pub fn add_word(word: &str) -> Packet {
-        Self::AddWord { word: word.to_string() }
+    pub fn attack(word: &str, weight: u32) -> Packet {
+        Self::AddWord { word: word.to_string(), weight }
     }
 }
 
@@ -260,27 +413,50 @@ impl Packet {
 pub struct Connection {
     pub stream: TcpStream,
     pub buf: Vec<u8>,
-    pub buf_pos: usize
+    pub buf_pos: usize,
+    /// packets that didn't fit in the socket yet, written as it empties
+    out: Vec<u8>
 }
 
 impl Connection {
     const CHUNK_SIZE: usize = 8192;
+    /// how long a dropped connection waits for its last packets to be sent
+    const LINGER: Duration = Duration::from_secs(1);
 
     pub fn new(stream: TcpStream) -> io::Result<Self> {
         stream.set_nonblocking(true)?;
         Ok(Connection {
             stream,
             buf: vec![],
-            buf_pos: 0
+            buf_pos: 0,
+            out: vec![]
         })
     }
 
+    /// writes as much of the outgoing packets as the socket takes without blocking
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.out.is_empty() {
+            match self.stream.write(&self.out) {
+                Ok(0) => return Err(io::Error::new(ErrorKind::WriteZero, "The connection was closed")),
+                Ok(n) => {
+                    self.out.drain(..n);
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e)
+            }
+        }
+
+        Ok(())
+    }
+
     fn try_read(&mut self, bytes: usize) -> io::Result<usize> {
         if self.buf_pos + bytes >= self.buf.len() {
-            self.buf.resize(self.buf.len() + Self::CHUNK_SIZE, 0);
+            // packets like word lists can be bigger than a chunk
+            self.buf.resize(self.buf_pos + bytes + Self::CHUNK_SIZE, 0);
         }
 
         match self.stream.read(&mut self.buf[self.buf_pos..self.buf_pos + bytes]) {
+            Ok(0) if bytes > 0 => Err(io::Error::new(ErrorKind::UnexpectedEof, "The connection was closed")),
             Ok(x) => {
                 self.buf_pos += x;
                 Ok(x)
@@ -291,6 +467,8 @@ impl Connection {
     }
 
     pub fn poll_next_packet(&mut self) -> io::Result<Option<Packet>> {
+        self.flush()?;
+
         while self.buf_pos < 4 {
             if self.try_read(4 - self.buf_pos)? == 0 {
                 return Ok(None);
@@ -334,11 +512,29 @@ impl Connection {
         return Err(io::Error::new(io::ErrorKind::TimedOut, "Timed out on Connection::next_packet"));
     }
 
+    /// queues a packet and sends as much of it as possible. the rest is sent while polling
     pub fn send_packet(&mut self, packet: Packet) -> io::Result<()> {
         let mut data = vec![];
         packet.write(&mut data)?;
-        self.stream.write_u32(data.len() as _)?;
-        self.stream.write_all(&data)
+        self.out.write_u32(data.len() as _)?;
+        self.out.extend(data);
+
+        self.flush()
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if self.out.is_empty() {
+            return;
+        }
+
+        let sent = self.stream.set_nonblocking(false)
+            .and_then(|_| self.stream.set_write_timeout(Some(Self::LINGER)))
+            .and_then(|_| self.stream.write_all(&self.out));
+        if let Err(e) = sent {
+            warn!("Dropped {} unsent bytes: {}", self.out.len(), e);
+        }
     }
 }
 
@@ -408,17 +604,22 @@ fn pair_up() -> io::Result<TcpStream> {
     }
 }
 
+/// fails unless the other player runs the same version
+pub fn check_version(major: VersionType, minor: VersionType) -> io::Result<()> {
+    if major != MAJOR_VERSION || minor != MINOR_VERSION {
+        return Err(io::Error::new(io::ErrorKind::Other, format!("Trying to connect to client with mismatched version. Your version = {}.{}. Their version = {}.{}.", MAJOR_VERSION, MINOR_VERSION, major, minor)));
+    }
+
+    Ok(())
+}
+
 pub fn connect() -> io::Result<Connection> {
     let mut conn = Connection::new(pair_up()?)?;
     
     conn.send_packet(Packet::client_info())?;
 
     match conn.next_packet(Duration::from_secs(10))? {
-        Packet::ClientInfo { major, minor } => {
-            if major != MAJOR_VERSION && minor != MINOR_VERSION {
-                return Err(io::Error::new(io::ErrorKind::Other, format!("Trying to connect to client with mismatched version. Your version = {}.{}. Their version = {}.{}.", MAJOR_VERSION, MINOR_VERSION, major, minor)));
-            }
-        },
+        Packet::ClientInfo { major, minor } => check_version(major, minor)?,
 
         packet => {
             return Err(io::Error::new(io::ErrorKind::Other, format!("First packet should be a ClientInfo packet. Received {:?}", packet)));
@@ -430,39 +631,105 @@ pub fn connect() -> io::Result<Connection> {
     Ok(conn)
 }
 
-const DUMMY_IP: &str = "localhost";
-const DUMMY_PORT: u16 = 5555;
-const DUMMY_WORD_LIST: &str = DEFAULT_WORD_LIST;
+pub(crate) const DUMMY_IP: &str = "localhost";
+pub(crate) const DUMMY_PORT: u16 = 5555;
 
-fn run_dummy() {
-    let stream = TcpStream::connect((DUMMY_IP, DUMMY_PORT)).unwrap();
-    let mut conn = Connection::new(stream).unwrap();
+/// a local bot's view of the current match, so that bots can play every online mode
+pub(crate) struct BotMatch {
+    pub rules: GameRules,
+    pub mode: GameMode,
+    pub start: Instant,
+    pub score: Score,
+    pub over: bool
+}
 
-    let words = fs::read_to_string(format!("res/words/{DUMMY_WORD_LIST}.txt")).unwrap();
-    let words = words.lines();
-    let words: Vec<_> = words.filter_map(|s| {
-        let trimmed = s.trim();
-        if trimmed.len() == 0 { return None; }
+impl BotMatch {
+    pub fn new() -> Self {
+        BotMatch {
+            rules: GameRules::default(),
+            mode: GameMode::Versus,
+            start: Instant::now(),
+            score: Score::default(),
+            over: false
+        }
+    }
+
+    pub fn restart(&mut self) {
+        *self = BotMatch { rules: self.rules.clone(), mode: self.mode, ..Self::new() };
+    }
+
+    /// counts a cleared word towards the score, keeping the opponent updated in time attack
+    pub fn cleared(&mut self, conn: &mut Connection, word: &str) -> io::Result<()> {
+        self.score.add_word(word);
+
+        if let GameMode::TimeAttack { .. } = self.mode {
+            conn.send_packet(Packet::Score { score: self.score, done: false })?;
+        }
 
-        let first_char: String = trimmed.chars().nth(0).unwrap().to_uppercase().collect();
-        let rest: String = trimmed.chars().skip(1).flat_map(|c| c.to_lowercase()).collect();
+        Ok(())
+    }
 
-        Some(format!("{}{}", first_char, rest))
-    }).collect();
+    /// ends a time attack once its time runs out, sending the final score
+    pub fn update(&mut self, conn: &mut Connection) -> io::Result<()> {
+        if let GameMode::TimeAttack { duration, .. } = self.mode {
+            if !self.over && self.start.elapsed().as_secs() >= duration {
+                conn.send_packet(Packet::Score { score: self.score, done: true })?;
+                self.over = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// updates the match from a packet sent by the player. returns true if the player wants to restart
+    pub fn handle_packet(&mut self, conn: &mut Connection, packet: &Packet) -> io::Result<bool> {
+        match packet {
+            Packet::ClientInfo { .. } => conn.send_packet(Packet::client_info())?,
+            Packet::MatchSettings { rules } => {
+                self.rules = rules.clone();
+                self.mode = rules.mode();
+                self.restart();
+            },
+            // bots never play with a handicap
+            Packet::Handicap { .. } => conn.send_packet(Packet::Handicap { handicap: Handicap::default() })?,
+            Packet::ILost {  } => self.over = true,
+            Packet::WaitingToRestart => {
+                self.restart();
+                return Ok(true);
+            },
+            _ => {}
+        }
+
+        Ok(false)
+    }
+}
+
+fn run_dummy(words: Vec<String>) {
+    let stream = TcpStream::connect((DUMMY_IP, DUMMY_PORT)).unwrap();
+    let mut conn = Connection::new(stream).unwrap();
 
     let secs_range = 2..=3;
 
     let mut rng = rand::thread_rng();
     let mut next_word_send = Instant::now() + Duration::from_secs(rng.gen_range(secs_range.clone()));
+    let mut bot_match = BotMatch::new();
 
     loop {
-        if next_word_send <= Instant::now() {
+        if !bot_match.over && next_word_send <= Instant::now() {
             next_word_send = Instant::now() + Duration::from_secs(rng.gen_range(secs_range.clone()));
             let word = &words[rng.gen_range(0..words.len())];
-            conn.send_packet(Packet::add_word(word)).unwrap();
+            for (word, weight) in bot_match.rules.attack.attack(word, &words, &mut rng) {
+                conn.send_packet(Packet::attack(&word, weight)).unwrap();
+            }
+            bot_match.cleared(&mut conn, word).unwrap();
         }
 
-        let packet = conn.poll_next_packet().unwrap();
+        bot_match.update(&mut conn).unwrap();
+
+        // the player left
+        let Ok(packet) = conn.poll_next_packet() else {
+            return;
+        };
 
         if packet.is_none() {
             thread::sleep(Duration::from_millis(100));
@@ -471,24 +738,182 @@ fn run_dummy() {
 
         let packet = packet.unwrap();
 
-        match packet {
-            Packet::ClientInfo {..} => {},
-            Packet::AddWord { word } => debug!("Dummy received {word}"),
-            Packet::ILost {  } => {},
-            Packet::WaitingToRestart => {
-                conn.send_packet(Packet::WaitingToRestart).unwrap();
-            }
+        if bot_match.handle_packet(&mut conn, &packet).unwrap() {
+            conn.send_packet(Packet::WaitingToRestart).unwrap();
+        }
+
+        if let Packet::AddWord { word, .. } = packet {
+            debug!("Dummy received {word}");
         }
     }
 }
 
-pub fn connect_to_dummy() -> io::Result<Connection> {
+/// replays a recorded run, giving up when the recording runs out unless that run was won
+fn run_ghost(run: GhostRun) {
+    let stream = TcpStream::connect((DUMMY_IP, DUMMY_PORT)).unwrap();
+    let mut conn = Connection::new(stream).unwrap();
+
+    let mut bot_match = BotMatch::new();
+    let mut next_event = 0;
+
+    loop {
+        if !bot_match.over {
+            let elapsed = bot_match.start.elapsed().as_secs_f32();
+
+            while next_event < run.events.len() && run.events[next_event].time <= elapsed {
+                let event = &run.events[next_event];
+                match event.kind {
+                    GhostEventKind::Cleared => bot_match.cleared(&mut conn, &event.word).unwrap(),
+                    GhostEventKind::Sent => {
+                        let weight = bot_match.rules.attack.sent_weight(&event.word);
+                        conn.send_packet(Packet::attack(&event.word, weight)).unwrap()
+                    }
+                }
+                next_event += 1;
+            }
+
+            if bot_match.mode == GameMode::Versus && elapsed >= run.length && !matches!(run.outcome, Some(GameOutcome::Win)) {
+                conn.send_packet(Packet::ILost {}).unwrap();
+                bot_match.over = true;
+            }
+        }
+
+        bot_match.update(&mut conn).unwrap();
+
+        let Ok(packet) = conn.poll_next_packet() else {
+            return;
+        };
+
+        if packet.is_none() {
+            thread::sleep(Duration::from_millis(10));
+            continue;
+        }
+
+        let packet = packet.unwrap();
 
+        if let Packet::MatchSettings { .. } | Packet::WaitingToRestart = packet {
+            next_event = 0;
+        }
+
+        if bot_match.handle_packet(&mut conn, &packet).unwrap() {
+            conn.send_packet(Packet::WaitingToRestart).unwrap();
+        }
+
+        if let Packet::AddWord { word, .. } = packet {
+            debug!("Ghost received {word}");
+        }
+    }
+}
+
+pub(crate) fn connect_to_local_bot<F: FnOnce() + Send + 'static>(bot: F) -> io::Result<Connection> {
     let listener = TcpListener::bind((DUMMY_IP, DUMMY_PORT))?;
 
-    thread::spawn(run_dummy);
+    thread::spawn(bot);
 
     let (stream, _addr) = listener.accept()?;
 
-    Ok(Connection::new(stream)?)
-}
\ No newline at end of file
+    Connection::new(stream)
+}
+
+/// `words` is the word list the dummy sends words from
+pub fn connect_to_dummy(words: Vec<String>) -> io::Result<Connection> {
+    connect_to_local_bot(move || run_dummy(words))
+}
+
+/// connects to a ghost replaying your best recorded run
+pub fn connect_to_ghost() -> io::Result<Connection> {
+    let run = GhostRun::load_opponent()?;
+    info!("Racing ghost of a {:.1}wpm run", run.wpm());
+
+    connect_to_local_bot(move || run_ghost(run))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a connection and the other end of it
+    fn pair() -> (Connection, TcpStream) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let other = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        (Connection::new(stream).unwrap(), other)
+    }
+
+    /// polls until a packet or an error arrives
+    fn poll(conn: &mut Connection) -> io::Result<Packet> {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            if let Some(packet) = conn.poll_next_packet()? {
+                return Ok(packet);
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        panic!("Nothing arrived");
+    }
+
+    #[test]
+    fn word_lists_bigger_than_the_socket_buffer() {
+        let (mut conn, other) = pair();
+        let mut sender = Connection::new(other).unwrap();
+        let list = WordList::read("res/words/lots_of.txt").unwrap();
+
+        // more than the socket holds before the other end reads it
+        const COPIES: usize = 12;
+        for _ in 0..COPIES {
+            sender.send_packet(Packet::WordList { name: "lots_of".to_owned(), list: list.clone() }).unwrap();
+        }
+        assert!(!sender.out.is_empty());
+
+        for _ in 0..COPIES {
+            let start = Instant::now();
+            let packet = loop {
+                assert!(start.elapsed() < Duration::from_secs(5), "Nothing arrived");
+                sender.poll_next_packet().unwrap();
+                if let Some(packet) = conn.poll_next_packet().unwrap() {
+                    break packet;
+                }
+            };
+
+            match packet {
+                Packet::WordList { name, list: received } => assert_eq!((name.as_str(), received), ("lots_of", list.clone())),
+                p => panic!("Expected the word list, received {:?}", p)
+            }
+        }
+    }
+
+    #[test]
+    fn closed_connection_is_an_error() {
+        let (mut conn, other) = pair();
+        drop(other);
+
+        assert_eq!(poll(&mut conn).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn closed_in_the_middle_of_a_packet() {
+        let (mut conn, mut other) = pair();
+        other.write_all(&100u32.to_be_bytes()).unwrap();
+        other.write_all(&[0, 9]).unwrap();
+        drop(other);
+
+        assert_eq!(poll(&mut conn).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn packets_arrive_before_the_close() {
+        let (mut conn, other) = pair();
+        let mut sender = Connection::new(other).unwrap();
+        let text = "bracket ".repeat(4000);
+        sender.send_packet(Packet::Bracket { text: text.clone() }).unwrap();
+        drop(sender);
+
+        match poll(&mut conn).unwrap() {
+            Packet::Bracket { text: received } => assert_eq!(received, text),
+            p => panic!("Expected the bracket, received {:?}", p)
+        }
+        assert_eq!(poll(&mut conn).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+}
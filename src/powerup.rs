@@ -0,0 +1,60 @@
+//! Rare highlighted words that trigger an effect when cleared.
+
+use ggez::graphics::Color;
+use rand::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUp {
+    /// clears your received words
+    Wipe,
+    /// the opponent can't clear words for a few seconds
+    Freeze,
+    /// your next cleared word is sent twice as many times
+    DoubleSend,
+    /// shuffles the opponent's words around
+    Shuffle
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 4] = [PowerUp::Wipe, PowerUp::Freeze, PowerUp::DoubleSend, PowerUp::Shuffle];
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self::ALL[rng.gen_range(0..Self::ALL.len())]
+    }
+
+    pub fn id(self) -> u8 {
+        match self {
+            PowerUp::Wipe => 0,
+            PowerUp::Freeze => 1,
+            PowerUp::DoubleSend => 2,
+            PowerUp::Shuffle => 3
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.id() == id)
+    }
+
+    /// whether the effect hits the opponent rather than whoever cleared the word
+    pub fn targets_opponent(self) -> bool {
+        matches!(self, PowerUp::Freeze | PowerUp::Shuffle)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerUp::Wipe => "wipe",
+            PowerUp::Freeze => "freeze",
+            PowerUp::DoubleSend => "x2",
+            PowerUp::Shuffle => "shuffle"
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            PowerUp::Wipe => Color::new(0.4, 0.9, 0.4, 0.6),
+            PowerUp::Freeze => Color::new(0.5, 0.8, 1.0, 0.6),
+            PowerUp::DoubleSend => Color::new(1.0, 0.8, 0.2, 0.6),
+            PowerUp::Shuffle => Color::new(0.9, 0.5, 1.0, 0.6)
+        }
+    }
+}
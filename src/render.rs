@@ -5,6 +5,7 @@ pub const TEXT_COLOR : Color       = Color::new(0.1, 0.1, 0.1, 1.0);
 pub const WINDOW_BG : Color        = Color::new(1.0, 1.0, 0.95, 1.0);
 pub const TEXT_BG_COLOR : Color    = Color::new(0.8, 0.8, 0.95, 0.5);
 pub const LIGHT_TEXT_COLOR : Color = Color::new(0.7, 0.7, 0.7, 1.0);
+pub const CORRECT_COLOR : Color    = Color::new(0.1, 0.6, 0.1, 1.0);
 
 /// shrinks a rectangle by v inwards on each side. each side decreases in length by 2v.
 pub fn shrink(rect: Rect, v: f32) -> Rect {
@@ -44,10 +45,23 @@ pub fn cut_right(rect: Rect, width: f32) -> (Rect, Rect) {
 }
 
 
+/// how [`render_words_in_rect`] draws words
+pub struct WordStyle<'a> {
+    pub font: &'a str,
+    pub font_size: f32,
+    pub color: Color,
+    /// how many characters of a word are crossed out
+    pub cross_out: &'a dyn Fn(&str) -> Option<usize>,
+    /// the color of the box a word is drawn on
+    pub highlight: &'a dyn Fn(&str) -> Option<Color>
+}
+
 /// render words clipped inside a rectangle, on top of a background with rounded corners
-pub fn render_words_in_rect(ctx: &mut Context, canvas: &mut Canvas, words: &Vec<String>, rect: Rect, font: &str, font_size: f32, cross_out: &str, color: Color) {
+pub fn render_words_in_rect(ctx: &mut Context, canvas: &mut Canvas, words: &Vec<String>, rect: Rect, style: WordStyle) {
     if rect.w < 0.0 || rect.h < 0.0 { return; }
 
+    let WordStyle { font, font_size, color, cross_out, highlight } = style;
+
     let mut x = rect.x;
     let mut y = rect.y;
     let prev = canvas.scissor_rect();
@@ -72,9 +86,6 @@ pub fn render_words_in_rect(ctx: &mut Context, canvas: &mut Canvas, words: &Vec<
 
     let column_width = rect.w / num_columns as f32;
 
-    let cross_out_lower = cross_out.to_lowercase();
-
-
     for (text, word) in texts {
         let end_y = y + word_height;
 
@@ -83,17 +94,28 @@ pub fn render_words_in_rect(ctx: &mut Context, canvas: &mut Canvas, words: &Vec<
             x += column_width;
         }
 
+        if let Some(highlight) = highlight(word) {
+            let dimensions = text.dimensions(ctx).unwrap();
+            canvas.draw(
+                &graphics::Mesh::new_rounded_rectangle(
+                    ctx,
+                    graphics::DrawMode::fill(),
+                    Rect { x: -4.0, y: 0.0, w: dimensions.w + 8.0, h: dimensions.h },
+                    8.0,
+                    highlight
+                ).unwrap(),
+                Vec2::new(x, y)
+            );
+        }
+
         canvas.draw(&text, Vec2::new(x, y));
 
-        if word.to_lowercase().starts_with(&cross_out_lower) && cross_out_lower.len() > 0 {
+        if let Some(chars) = cross_out(word) {
             let positions = text.glyph_positions(ctx).unwrap();
             let dimensions = text.dimensions(ctx).unwrap();
-            
-            let cross_out_len = if cross_out_lower.len() == word.len() {
-                dimensions.w
-            } else {
-                positions[cross_out_lower.len()].x
-            };
+
+            // glyphs are per character, not per byte
+            let cross_out_len = positions.get(chars).map_or(dimensions.w, |p| p.x);
             // cross out line
             canvas.draw(
                 &graphics::Mesh::new_line(
@@ -115,6 +137,92 @@ pub fn render_words_in_rect(ctx: &mut Context, canvas: &mut Canvas, words: &Vec<
     canvas.set_scissor_rect(prev).unwrap();
 }
 
+/// the typed text with every character green if it matches `target` and red if it doesn't, followed by a cursor.
+/// only the last `max_chars` characters are shown
+pub fn typed_text_with_correctness(typed: &str, target: &str, max_chars: usize, font: &str, font_size: f32) -> Text {
+    let skip = typed.chars().count().saturating_sub(max_chars);
+    let target_chars = target.chars().map(Some).chain(std::iter::repeat(None));
+
+    let mut text = Text::default();
+    for (typed_char, target_char) in typed.chars().zip(target_chars).skip(skip) {
+        text.add(
+            TextFragment::new(typed_char)
+                .color(if Some(typed_char) == target_char { CORRECT_COLOR } else { Color::RED })
+                .font(font)
+                .scale(font_size)
+        );
+    }
+    text.add(TextFragment::new("|").color(TEXT_COLOR).font(font).scale(font_size));
+
+    text
+}
+
+/// render a sentence wrapped inside a rectangle, with the correctly typed start of it in green
+pub fn render_quote_in_rect(ctx: &mut Context, canvas: &mut Canvas, quote: &str, typed: &str, rect: Rect, font: &str, font_size: f32) {
+    if rect.w < 0.0 || rect.h < 0.0 { return; }
+
+    let prev = canvas.scissor_rect();
+    canvas.draw(&graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), rect, TEXT_BG_COLOR).unwrap(), Vec2::new(0.0, 0.0));
+    canvas.set_scissor_rect(rect).unwrap();
+
+    let correct = quote.chars().zip(typed.chars()).take_while(|(a, b)| a == b).count();
+    let split = quote.char_indices().nth(correct).map_or(quote.len(), |(i, _)| i);
+
+    let mut text = Text::default();
+    text.add(TextFragment::new(&quote[..split]).color(CORRECT_COLOR));
+    text.add(TextFragment::new(&quote[split..]).color(TEXT_COLOR));
+    text.set_font(font);
+    text.set_scale(font_size);
+    text.set_bounds(Vec2::new(rect.w, f32::INFINITY));
+    text.set_wrap(true);
+
+    canvas.draw(&text, Vec2::new(rect.x, rect.y));
+
+    canvas.set_scissor_rect(prev).unwrap();
+}
+
+/// render the remaining lines of a code snippet, with the line being typed highlighted
+/// and its correctly typed start in green. indentation doesn't need to be typed
+pub fn render_code_in_rect(ctx: &mut Context, canvas: &mut Canvas, lines: &[String], typed: &str, rect: Rect, font: &str, font_size: f32) {
+    if rect.w < 0.0 || rect.h < 0.0 { return; }
+
+    let prev = canvas.scissor_rect();
+    canvas.draw(&graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), rect, TEXT_BG_COLOR).unwrap(), Vec2::new(0.0, 0.0));
+    canvas.set_scissor_rect(rect).unwrap();
+
+    let line_height = font_size * 1.1;
+    let mut y = rect.y;
+
+    for (i, line) in lines.iter().enumerate() {
+        let mut text = Text::default();
+
+        if i == 0 {
+            canvas.draw(
+                &graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), Rect { x: rect.x, y, w: rect.w, h: line_height }, TEXT_BG_COLOR).unwrap(),
+                Vec2::new(0.0, 0.0)
+            );
+
+            let code = line.trim_start();
+            let correct = code.chars().zip(typed.chars()).take_while(|(a, b)| a == b).count();
+            let split = code.char_indices().nth(correct).map_or(code.len(), |(i, _)| i);
+
+            text.add(TextFragment::new(&line[..line.len() - code.len()]));
+            text.add(TextFragment::new(&code[..split]).color(CORRECT_COLOR));
+            text.add(TextFragment::new(&code[split..]).color(TEXT_COLOR));
+        } else {
+            text.add(TextFragment::new(line.as_str()).color(LIGHT_TEXT_COLOR));
+        }
+
+        text.set_font(font);
+        text.set_scale(font_size);
+        canvas.draw(&text, Vec2::new(rect.x, y));
+
+        y += line_height;
+    }
+
+    canvas.set_scissor_rect(prev).unwrap();
+}
+
 pub fn center_text_in_rect(ctx: &mut Context, canvas: &mut Canvas, text: &Text, rect: Rect) {
     if rect.w < 0.0 || rect.h < 0.0 { return; }
     let prev = canvas.scissor_rect();
@@ -130,6 +238,26 @@ pub fn center_text_in_rect(ctx: &mut Context, canvas: &mut Canvas, text: &Text,
     canvas.set_scissor_rect(prev).unwrap();
 }
 
+/// renders a tournament bracket, shrinking the text until every line fits in the rectangle
+pub fn render_bracket(ctx: &mut Context, canvas: &mut Canvas, bracket: &str, rect: Rect) {
+    let lines = bracket.lines().count().max(1) as f32;
+    let longest = bracket.lines().map(|l| l.chars().count()).max().unwrap_or(1).max(1) as f32;
+    // courier characters are about 0.6 of the font size wide
+    let font_size = (rect.h / lines).min(rect.w / longest / 0.6).min(40.0);
+
+    center_text_in_rect(
+        ctx,
+        canvas,
+        &Text::new(
+            TextFragment::new(bracket)
+                .color(TEXT_COLOR)
+                .scale(font_size)
+                .font("courier_new"),
+        ),
+        rect,
+    );
+}
+
 fn lerp(start: f32, end: f32, t: f32) -> f32 {
     start * (1.0 - t) + end * t
 }
@@ -0,0 +1,665 @@
+//! Gameplay numbers, loaded from toml files in `res/rules` so that variants don't need a recompile.
+//!
+//! Every field is optional, missing ones take the value of the default rules:
+//! ```toml
+//! name = "Time attack 60s"
+//! spawn_delay = 0.3
+//! min_board_words = 5
+//! max_board_words = 20
+//! ignore_accents = false   # lets "e" clear "é", for lists in languages with accents
+//! adaptive = false         # puts the words you type slowest or miss most on the board more often
+//!
+//! [limit]
+//! start = 20
+//! shrink_start = 120.0
+//! shrink_interval = 6.0
+//! min = 0
+//!
+//! [win_condition]
+//! type = "time_attack"
+//! duration = 60
+//! score_by = "words"
+//!
+//! [send]
+//! copies = 1
+//! send_received = false
+//!
+//! [power_ups]
+//! chance = 0.03
+//! freeze_secs = 3.0
+//!
+//! [combo]
+//! window_secs = 4.0
+//! clears_per_bonus = 3
+//! max_bonus = 3
+//!
+//! [incoming]
+//! delay_secs = 3.0
+//! cancel = true
+//!
+//! [attack]
+//! base_length = 6
+//! chars_per_weight = 3
+//! max_weight = 3
+//! heavy = "weight"
+//!
+//! [survival]
+//! wave_secs = 20.0
+//! first_interval = 2.0
+//! interval_factor = 0.85
+//! min_interval = 0.4
+//! first_min_length = 3
+//! length_per_wave = 1
+//!
+//! [mistakes]
+//! type = "lockout"   # or "none", "garbage" with `words = 1`, "strikes" with `max = 3`
+//! secs = 1.0
+//!
+//! [difficulty]
+//! type = "ramp"   # or "uniform" to pick every word as often
+//! start = 0.0     # word difficulty aimed for at the start, from 0 to 1
+//! end = 1.0
+//! secs = 180.0    # how long it takes to get from start to end
+//! curve = 1.0     # above 1 stays easy for longer, below 1 gets hard sooner
+//! spread = 0.2    # how far from the aimed difficulty words still come up often
+//!
+//! [text]
+//! type = "words"   # or "quotes"/"code" with `file = "quotes"` and `words_per_send = 3`
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::text::{is_word_char, same_word};
+use crate::word_game::{GameMode, ScoreBy, bonus_word, parse_word_list};
+
+pub const RULES_DIR: &str = "res/rules";
+pub const QUOTES_DIR: &str = "res/quotes";
+pub const SNIPPETS_DIR: &str = "res/snippets";
+/// separates the snippets in a snippet file
+const SNIPPET_SEPARATOR: &str = "---";
+
+/// how many received words a player can hold over the course of a match
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitCurve {
+    pub start: usize,
+    /// seconds into the match at which the limit starts shrinking
+    pub shrink_start: f32,
+    /// seconds between the limit shrinking by one
+    pub shrink_interval: f32,
+    pub min: usize
+}
+
+impl Default for LimitCurve {
+    fn default() -> Self {
+        LimitCurve {
+            start: 20,
+            shrink_start: 120.0,
+            shrink_interval: 6.0,
+            min: 0
+        }
+    }
+}
+
+impl LimitCurve {
+    pub fn limit_at(&self, elapsed_secs: f32) -> usize {
+        if elapsed_secs < self.shrink_start {
+            return self.start;
+        }
+
+        let shrunk = ((elapsed_secs - self.shrink_start) / self.shrink_interval.max(0.001)) as usize;
+        self.start.saturating_sub(shrunk).max(self.min)
+    }
+
+    /// seconds into the match at which the limit reaches its minimum
+    pub fn end(&self) -> f32 {
+        self.shrink_start + (self.start.saturating_sub(self.min)) as f32 * self.shrink_interval
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WinCondition {
+    /// you lose when your received words overflow the limit
+    Overflow,
+    /// whoever cleared the most in `duration` seconds wins
+    TimeAttack {
+        duration: u64,
+        score_by: ScoreBy
+    }
+}
+
+/// what happens to cleared words
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SendRules {
+    /// how many times each word cleared from your board is sent to the opponent
+    pub copies: u32,
+    /// whether clearing a received word sends it back
+    pub send_received: bool
+}
+
+impl Default for SendRules {
+    fn default() -> Self {
+        SendRules {
+            copies: 1,
+            send_received: false
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerUpRules {
+    /// chance of a new word on the board being a power-up
+    pub chance: f32,
+    pub freeze_secs: f32
+}
+
+impl Default for PowerUpRules {
+    fn default() -> Self {
+        PowerUpRules {
+            chance: 0.0,
+            freeze_secs: 3.0
+        }
+    }
+}
+
+/// words sent by the opponent wait in an incoming lane before landing on your board
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IncomingRules {
+    /// seconds a received word waits before landing. 0 lands words immediately
+    pub delay_secs: f32,
+    /// whether words you send first cancel out words still waiting to land
+    pub cancel: bool
+}
+
+impl Default for IncomingRules {
+    fn default() -> Self {
+        IncomingRules {
+            delay_secs: 0.0,
+            cancel: false
+        }
+    }
+}
+
+/// letters that make a word harder to type, counting as an extra character towards its weight
+const RARE_LETTERS: &str = "jkqvxz";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeavyAttack {
+    /// heavy words count more toward the receiver's limit
+    Weight,
+    /// heavy words arrive together with extra words
+    Split
+}
+
+/// how hard a sent word hits, based on its length and difficulty
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttackRules {
+    /// words up to this long weigh 1
+    pub base_length: usize,
+    /// extra characters needed for each extra point of weight. 0 makes every word weigh 1
+    pub chars_per_weight: usize,
+    pub max_weight: u32,
+    pub heavy: HeavyAttack
+}
+
+impl Default for AttackRules {
+    fn default() -> Self {
+        AttackRules {
+            base_length: 6,
+            chars_per_weight: 0,
+            max_weight: 3,
+            heavy: HeavyAttack::Weight
+        }
+    }
+}
+
+impl AttackRules {
+    pub fn weight(&self, word: &str) -> u32 {
+        let lower = word.to_lowercase();
+        let rare = lower.chars().filter(|c| RARE_LETTERS.contains(*c)).count();
+        let extra = (lower.chars().count() + rare).saturating_sub(self.base_length);
+
+        (1 + extra.checked_div(self.chars_per_weight).unwrap_or(0) as u32).min(self.max_weight.max(1))
+    }
+
+    /// the weight `word` is sent with. split attacks are always sent as words weighing 1
+    pub fn sent_weight(&self, word: &str) -> u32 {
+        match self.heavy {
+            HeavyAttack::Weight => self.weight(word),
+            HeavyAttack::Split => 1
+        }
+    }
+
+    /// the words, and their weights, that clearing `word` sends to the opponent
+    pub fn attack(&self, word: &str, list: &[String], rng: &mut impl Rng) -> Vec<(String, u32)> {
+        let mut words = vec![(word.to_owned(), self.sent_weight(word))];
+
+        if self.heavy == HeavyAttack::Split {
+            for _ in 1..self.weight(word) {
+                words.push((bonus_word(list, 0, rng), 1));
+            }
+        }
+
+        words
+    }
+}
+
+/// what happens when a submission doesn't match any word
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MistakePenalty {
+    None,
+    /// typing is ignored for a while
+    Lockout {
+        secs: f32
+    },
+    /// random words are added to your own received words
+    Garbage {
+        words: u32
+    },
+    /// you lose once you reach `max` mistakes
+    Strikes {
+        max: u32
+    }
+}
+
+/// how words are picked from the word list for your board, see [`crate::word_list::WordList::difficulties`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WordDifficulty {
+    Uniform,
+    /// words close to a difficulty that goes from `start` to `end` over `secs` come up the most
+    Ramp {
+        start: f32,
+        end: f32,
+        secs: f32,
+        curve: f32,
+        spread: f32
+    }
+}
+
+impl WordDifficulty {
+    /// how often a word of difficulty `difficulty` should come up `time` seconds into the game, relative to others
+    pub fn weight(&self, difficulty: f32, time: f32) -> f32 {
+        match *self {
+            WordDifficulty::Uniform => 1.0,
+            WordDifficulty::Ramp { start, end, secs, curve, spread } => {
+                let progress = (time / secs.max(0.001)).clamp(0.0, 1.0).powf(curve);
+                let target = start + (end - start) * progress;
+                let distance = (difficulty - target) / spread.max(0.001);
+
+                (-distance * distance).exp()
+            }
+        }
+    }
+}
+
+/// clearing words quickly without typos builds a streak that sends bonus words
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ComboRules {
+    /// most seconds between two clears that keeps the streak going
+    pub window_secs: f32,
+    /// length of streak needed for each bonus word. 0 disables combos
+    pub clears_per_bonus: u32,
+    pub max_bonus: u32
+}
+
+impl Default for ComboRules {
+    fn default() -> Self {
+        ComboRules {
+            window_secs: 4.0,
+            clears_per_bonus: 0,
+            max_bonus: 3
+        }
+    }
+}
+
+impl ComboRules {
+    /// how many bonus words a clear sends with a streak of `combo`
+    pub fn bonus(&self, combo: u32) -> u32 {
+        combo.checked_div(self.clears_per_bonus).unwrap_or(0).min(self.max_bonus)
+    }
+}
+
+/// the waves of words sent at the player in survival, each one faster and with longer words than the last
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurvivalRules {
+    pub wave_secs: f32,
+    /// seconds between words sent during the first wave
+    pub first_interval: f32,
+    /// the interval is multiplied by this every wave
+    pub interval_factor: f32,
+    pub min_interval: f32,
+    /// shortest words sent during the first wave
+    pub first_min_length: usize,
+    /// how much longer the shortest words get every wave
+    pub length_per_wave: usize
+}
+
+impl Default for SurvivalRules {
+    fn default() -> Self {
+        SurvivalRules {
+            wave_secs: 20.0,
+            first_interval: 2.0,
+            interval_factor: 0.85,
+            min_interval: 0.4,
+            first_min_length: 3,
+            length_per_wave: 1
+        }
+    }
+}
+
+impl SurvivalRules {
+    /// the wave reached after `time` seconds, starting at 1
+    pub fn wave(&self, time: f32) -> u32 {
+        (time / self.wave_secs.max(1.0)) as u32 + 1
+    }
+
+    /// seconds between words sent during `wave`
+    pub fn interval(&self, wave: u32) -> f32 {
+        (self.first_interval * self.interval_factor.powi(wave as i32 - 1)).max(self.min_interval)
+    }
+
+    /// shortest words sent during `wave`
+    pub fn min_length(&self, wave: u32) -> usize {
+        self.first_min_length + self.length_per_wave * (wave as usize - 1)
+    }
+}
+
+/// what players type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextSource {
+    /// single words from the word list
+    Words,
+    /// sentences from `res/quotes/<file>.txt`, typed exactly. a quote sends one word per `words_per_send`
+    Quotes {
+        file: String,
+        words_per_send: u32
+    },
+    /// snippets from `res/snippets/<file>` separated by `---`, typed a line at a time without indentation
+    Code {
+        file: String,
+        words_per_send: u32
+    }
+}
+
+impl TextSource {
+    /// the texts to put on the board, or None to use the word list
+    pub fn load(&self) -> io::Result<Option<Vec<String>>> {
+        match self {
+            TextSource::Words => Ok(None),
+            TextSource::Quotes { file, .. } => {
+                let text = fs::read_to_string(Path::new(QUOTES_DIR).join(format!("{file}.txt")))?;
+                let quotes: Vec<String> = text.lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+
+                if quotes.is_empty() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Quote file {file} is empty")));
+                }
+
+                Ok(Some(quotes))
+            },
+            TextSource::Code { file, .. } => {
+                let text = fs::read_to_string(Path::new(SNIPPETS_DIR).join(file))?;
+                let mut snippets = vec![];
+                let mut snippet = String::new();
+
+                for line in text.lines().chain([SNIPPET_SEPARATOR]) {
+                    if line.trim() == SNIPPET_SEPARATOR {
+                        if !snippet.trim().is_empty() {
+                            snippets.push(std::mem::take(&mut snippet));
+                        }
+                        snippet.clear();
+                    } else if !line.trim().is_empty() {
+                        snippet.push_str(&line.trim_end().replace('\t', "    "));
+                        snippet.push('\n');
+                    }
+                }
+
+                if snippets.is_empty() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Snippet file {file} is empty")));
+                }
+
+                Ok(Some(snippets))
+            }
+        }
+    }
+
+    /// whether board texts have to be cleared in order, one at a time
+    pub fn in_order(&self) -> bool {
+        !matches!(self, TextSource::Words)
+    }
+
+    /// whether `typed` clears `text` from the board
+    pub fn matches(&self, text: &str, typed: &str, ignore_accents: bool) -> bool {
+        match self {
+            TextSource::Words => same_word(text, typed, ignore_accents),
+            TextSource::Quotes { .. } => text == typed,
+            TextSource::Code { .. } => text.trim_start() == typed
+        }
+    }
+
+    /// whether `c` can be typed
+    pub fn allows(&self, c: char) -> bool {
+        match self {
+            TextSource::Words => is_word_char(c),
+            _ => !c.is_control()
+        }
+    }
+
+    /// the words sent to the opponent when `text` is cleared from the board
+    pub fn attack_words(&self, text: &str) -> Vec<String> {
+        match self {
+            TextSource::Words => vec![text.to_owned()],
+            TextSource::Quotes { words_per_send, .. } | TextSource::Code { words_per_send, .. } => {
+                let mut words: Vec<String> = if let TextSource::Code { .. } = self {
+                    // identifiers, split at symbols and underscores
+                    text.split(|c: char| !c.is_alphabetic())
+                        .filter(|w| w.chars().count() > 1)
+                        .map(str::to_owned)
+                        .collect()
+                } else {
+                    text.split_whitespace()
+                        .map(|w| w.chars().filter(|c| c.is_alphabetic()).collect::<String>())
+                        .filter(|w| !w.is_empty())
+                        .collect()
+                };
+                let count = words.len().div_ceil((*words_per_send).max(1) as usize);
+
+                // the longest words hit hardest
+                words.sort_by_key(|w| std::cmp::Reverse(w.chars().count()));
+                parse_word_list(&words[..count].join("\n"))
+            }
+        }
+    }
+}
+
+/// a per-player advantage for mixed-skill matches, exchanged when connecting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Handicap {
+    pub name: &'static str,
+    /// added to your limit
+    pub extra_limit: usize,
+    /// added to how long words sent to you wait in the incoming lane
+    pub extra_delay_secs: f32,
+    /// percentage of the words sent to you that actually arrive
+    pub received_percent: u32
+}
+
+pub const HANDICAPS: &[Handicap] = &[
+    Handicap { name: "none",   extra_limit: 0,  extra_delay_secs: 0.0, received_percent: 100 },
+    Handicap { name: "light",  extra_limit: 5,  extra_delay_secs: 1.0, received_percent: 90 },
+    Handicap { name: "medium", extra_limit: 10, extra_delay_secs: 2.0, received_percent: 75 },
+    Handicap { name: "heavy",  extra_limit: 20, extra_delay_secs: 3.0, received_percent: 50 },
+];
+
+impl Default for Handicap {
+    fn default() -> Self {
+        HANDICAPS[0]
+    }
+}
+
+impl Handicap {
+    pub fn id(&self) -> u8 {
+        HANDICAPS.iter().position(|h| h == self).unwrap_or(0) as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        HANDICAPS.get(id as usize).copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameRules {
+    pub name: String,
+    /// seconds between new words being added to a full-enough board
+    pub spawn_delay: f32,
+    /// boards with fewer words than this are refilled immediately
+    pub min_board_words: usize,
+    pub max_board_words: usize,
+    /// whether typed words match regardless of accents
+    pub ignore_accents: bool,
+    /// whether words are picked from your keystroke stats, see [`crate::keystrokes`]
+    pub adaptive: bool,
+    pub limit: LimitCurve,
+    pub win_condition: WinCondition,
+    pub send: SendRules,
+    pub power_ups: PowerUpRules,
+    pub combo: ComboRules,
+    pub incoming: IncomingRules,
+    pub attack: AttackRules,
+    pub mistakes: MistakePenalty,
+    pub difficulty: WordDifficulty,
+    pub survival: SurvivalRules,
+    pub text: TextSource
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            name: "default".to_owned(),
+            spawn_delay: 0.3,
+            min_board_words: 5,
+            max_board_words: 20,
+            ignore_accents: false,
+            adaptive: false,
+            limit: LimitCurve::default(),
+            win_condition: WinCondition::Overflow,
+            send: SendRules::default(),
+            power_ups: PowerUpRules::default(),
+            combo: ComboRules::default(),
+            incoming: IncomingRules::default(),
+            attack: AttackRules::default(),
+            mistakes: MistakePenalty::None,
+            difficulty: WordDifficulty::Uniform,
+            survival: SurvivalRules::default(),
+            text: TextSource::Words
+        }
+    }
+}
+
+impl GameRules {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        toml::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    pub fn to_toml(&self) -> io::Result<String> {
+        toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    /// loads a rules file. rules without a name are named after the file
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        let mut rules = Self::parse(&fs::read_to_string(path)?)?;
+        if rules.name == Self::default().name {
+            if let Some(stem) = path.file_stem() {
+                rules.name = stem.to_string_lossy().into_owned();
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// finds rules by name: "default", the name of a file in [`RULES_DIR`], or a path to a rules file
+    pub fn find(name: &str) -> io::Result<Self> {
+        if name == "default" {
+            return Ok(Self::default());
+        }
+
+        let in_rules_dir = Path::new(RULES_DIR).join(format!("{name}.toml"));
+        if in_rules_dir.exists() {
+            Self::load(in_rules_dir)
+        } else {
+            Self::load(name)
+        }
+    }
+
+    /// the default rules followed by every rules file in [`RULES_DIR`]
+    pub fn load_all() -> Vec<Self> {
+        let mut all = vec![Self::default()];
+
+        let mut paths: Vec<_> = match fs::read_dir(RULES_DIR) {
+            Ok(dir) => dir.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(e) => {
+                warn!("Couldn't read rules directory: {}", e);
+                vec![]
+            }
+        };
+        paths.sort();
+
+        for path in paths.into_iter().filter(|p| p.extension().is_some_and(|e| e == "toml")) {
+            match Self::load(&path) {
+                Ok(rules) => all.push(rules),
+                Err(e) => error!("Failed to load rules {}: {}", path.display(), e)
+            }
+        }
+
+        all
+    }
+
+    /// the kind of online match these rules make
+    pub fn mode(&self) -> GameMode {
+        match self.win_condition {
+            WinCondition::Overflow => GameMode::Versus,
+            WinCondition::TimeAttack { duration, score_by } => GameMode::TimeAttack { duration, score_by }
+        }
+    }
+
+    /// whether these rules can be played in `mode`
+    pub fn suits(&self, mode: GameMode) -> bool {
+        match mode {
+            GameMode::Versus | GameMode::Practice { .. } => self.win_condition == WinCondition::Overflow,
+            GameMode::TimeAttack { .. } => self.win_condition != WinCondition::Overflow,
+            GameMode::Survival | GameMode::Daily { .. } => self.win_condition == WinCondition::Overflow && !self.text.in_order()
+        }
+    }
+
+    pub fn should_spawn(&self, since_last_word: f32, board_words: usize) -> bool {
+        // quotes and snippets are typed one at a time
+        if self.text.in_order() {
+            return board_words == 0;
+        }
+
+        (since_last_word > self.spawn_delay || board_words < self.min_board_words)
+            && board_words < self.max_board_words
+    }
+}
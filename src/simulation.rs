@@ -0,0 +1,323 @@
+use std::fs;
+use std::io::{self, Write};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::board::{Board, Submission};
+use crate::powerup::PowerUp;
+use crate::rules::{GameRules, Handicap, WinCondition, HANDICAPS};
+use crate::word_game::{DEFAULT_WORD_LIST, Score, load_word_list};
+use crate::word_list::csv_field;
+
+/// length of one simulation step in seconds. matches the usual frame time of the real game
+const TICK: f32 = 1.0 / 60.0;
+/// overflow matches still going this long after the limit has stopped shrinking are counted as draws
+const OVERTIME: f32 = 60.0;
+
+#[derive(Debug, Clone)]
+pub struct BotProfile {
+    pub name: &'static str,
+    pub chars_per_sec: f32,
+    /// time spent picking a word before starting to type it
+    pub reaction_secs: f32,
+    /// chance that a typed word doesn't match and has to be typed again
+    pub mistake_rate: f32,
+    /// starts clearing received words once it is this close to the limit
+    pub defensive_margin: usize
+}
+
+pub const BOT_PROFILES: &[BotProfile] = &[
+    BotProfile { name: "novice",  chars_per_sec: 3.5,  reaction_secs: 0.8, mistake_rate: 0.10, defensive_margin: 2 },
+    BotProfile { name: "average", chars_per_sec: 6.0,  reaction_secs: 0.5, mistake_rate: 0.06, defensive_margin: 4 },
+    BotProfile { name: "fast",    chars_per_sec: 8.5,  reaction_secs: 0.35, mistake_rate: 0.04, defensive_margin: 5 },
+    BotProfile { name: "pro",     chars_per_sec: 11.5, reaction_secs: 0.25, mistake_rate: 0.02, defensive_margin: 6 },
+];
+
+impl BotProfile {
+    pub fn find(name: &str) -> Option<&'static BotProfile> {
+        BOT_PROFILES.iter().find(|p| p.name == name)
+    }
+}
+
+#[derive(Debug, Default)]
+struct SimPlayer {
+    board: Board,
+    score: Score,
+    handicap: Handicap,
+
+    /// word currently being typed, and the time at which it will be submitted
+    typing: Option<(String, f32)>
+}
+
+impl SimPlayer {
+    fn new(handicap: Handicap) -> Self {
+        SimPlayer { handicap, ..Default::default() }
+    }
+
+    fn update_board(&mut self, rules: &GameRules, words: &[String], time: f32, rng: &mut StdRng) {
+        if self.board.spawn_due(rules, time) {
+            let idx = rng.gen_range(0..words.len());
+            self.board.add_word(rules, words, idx, rng);
+        }
+    }
+
+    fn receive(&mut self, rules: &GameRules, words: Vec<(String, u32)>, time: f32) {
+        for (word, weight) in words {
+            self.board.receive(rules, self.handicap, word, weight, time);
+        }
+
+        self.board.land_incoming(time);
+    }
+
+    fn has_lost(&self, rules: &GameRules, limit: usize) -> bool {
+        self.board.received_load() > limit || self.board.struck_out(rules)
+    }
+
+    /// advances the typing of the bot, returning the words that should be sent to the opponent and the power-ups
+    /// that hit them
+    fn update_typing(&mut self, rules: &GameRules, profile: &BotProfile, words: &[String], limit: usize, time: f32, rng: &mut StdRng) -> (Vec<(String, u32)>, Vec<PowerUp>) {
+        let mut submission = Submission::default();
+
+        if let Some((word, done_at)) = &self.typing {
+            // a word finished while frozen is submitted once the freeze is over
+            if *done_at > time || self.board.is_frozen(time) {
+                return (vec![], vec![]);
+            }
+
+            if word.is_empty() {
+                // a lockout just ended
+            } else if rng.gen::<f32>() >= profile.mistake_rate {
+                submission = self.board.submit(rules, word, false, words, time, rng);
+                for w in submission.cleared.iter() {
+                    self.score.add_word(w);
+                }
+            } else {
+                self.board.punish_mistake(rules, words, time, rng);
+
+                if self.board.is_locked_out(time) {
+                    // the next word can only be started once the lockout is over
+                    self.typing = Some((String::new(), self.board.locked_until));
+                    return (vec![], vec![]);
+                }
+            }
+
+            self.typing = None;
+        }
+
+        let board = &self.board;
+        let target = if !board.received_words.is_empty() && board.received_load() + profile.defensive_margin > limit {
+            Some(board.received_words[0].clone())
+        } else if board.current_words.is_empty() {
+            None
+        } else if rules.text.in_order() {
+            Some(board.current_words[0].clone())
+        } else {
+            Some(board.current_words[rng.gen_range(0..board.current_words.len())].clone())
+        };
+
+        if let Some(word) = target {
+            // +1 for pressing enter
+            let typing_time = profile.reaction_secs + (word.chars().count() + 1) as f32 / profile.chars_per_sec;
+            self.typing = Some((word, time + typing_time));
+        }
+
+        (submission.sent, submission.power_ups)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MatchResult {
+    /// index of the winning bot, or None for a draw
+    pub winner: Option<usize>,
+    pub length: f32,
+    /// the limit that was exceeded, if anyone overflowed
+    pub overflow_limit: Option<usize>
+}
+
+pub fn simulate_match(rules: &GameRules, bots: [&BotProfile; 2], handicaps: [Handicap; 2], words: &[String], rng: &mut StdRng) -> MatchResult {
+    let mut players = handicaps.map(SimPlayer::new);
+    let max_length = match rules.win_condition {
+        WinCondition::Overflow => rules.limit.end() + OVERTIME,
+        WinCondition::TimeAttack { duration, .. } => duration as f32
+    };
+    let mut time = 0.0;
+
+    while time < max_length {
+        time += TICK;
+        let limit = rules.limit.limit_at(time);
+
+        for player in players.iter_mut() {
+            player.update_board(rules, words, time, rng);
+        }
+
+        for i in 0..2 {
+            let player_limit = limit + players[i].handicap.extra_limit;
+            let (sent, power_ups) = players[i].update_typing(rules, bots[i], words, player_limit, time, rng);
+            players[1 - i].receive(rules, sent, time);
+
+            for power_up in power_ups {
+                players[1 - i].board.apply_power_up(rules, power_up, time, rng);
+            }
+        }
+
+        if let WinCondition::TimeAttack { .. } = rules.win_condition {
+            continue;
+        }
+
+        let overflowed = players.each_ref().map(|p| p.has_lost(rules, limit + p.handicap.extra_limit));
+
+        let winner = match overflowed {
+            [false, false] => continue,
+            [true, true] => None,
+            [true, false] => Some(1),
+            [false, true] => Some(0)
+        };
+
+        return MatchResult { winner, length: time, overflow_limit: Some(limit) };
+    }
+
+    let winner = match rules.win_condition {
+        WinCondition::TimeAttack { score_by, .. } => {
+            match players[0].score.get(score_by).cmp(&players[1].score.get(score_by)) {
+                std::cmp::Ordering::Greater => Some(0),
+                std::cmp::Ordering::Less => Some(1),
+                std::cmp::Ordering::Equal => None
+            }
+        },
+        WinCondition::Overflow => None
+    };
+
+    MatchResult { winner, length: time, overflow_limit: None }
+}
+
+#[derive(Debug, Default)]
+struct MatchupStats {
+    matches: u32,
+    wins: [u32; 2],
+    draws: u32,
+    total_length: f32,
+    overflow_times: Vec<f32>,
+    overflows_before_shrink: u32
+}
+
+impl MatchupStats {
+    fn record(&mut self, rules: &GameRules, result: MatchResult) {
+        self.matches += 1;
+        self.total_length += result.length;
+
+        match result.winner {
+            Some(i) => self.wins[i] += 1,
+            None => self.draws += 1
+        }
+
+        if result.overflow_limit.is_some() {
+            self.overflow_times.push(result.length);
+            if result.length < rules.limit.shrink_start {
+                self.overflows_before_shrink += 1;
+            }
+        }
+    }
+
+    fn csv_row(&self, rules: &GameRules, bots: [&BotProfile; 2], handicaps: [Handicap; 2]) -> String {
+        let matches = self.matches.max(1) as f32;
+        let overflows = self.overflow_times.len().max(1) as f32;
+        let (min_overflow, max_overflow) = self.overflow_times.iter().fold((f32::NAN, f32::NAN), |(min, max), t| (t.min(min), t.max(max)));
+
+        format!(
+            "{},{},{},{},{},{},{:.4},{:.4},{:.4},{:.2},{:.2},{:.2},{:.2},{:.4}",
+            csv_field(&rules.name), bots[0].name, bots[1].name, handicaps[0].name, handicaps[1].name, self.matches,
+            self.wins[0] as f32 / matches,
+            self.wins[1] as f32 / matches,
+            self.draws as f32 / matches,
+            self.total_length / matches,
+            self.overflow_times.iter().sum::<f32>() / overflows,
+            min_overflow,
+            max_overflow,
+            self.overflows_before_shrink as f32 / overflows
+        )
+    }
+}
+
+const CSV_HEADER: &str = "rules,bot_a,bot_b,handicap_a,handicap_b,matches,a_win_rate,b_win_rate,draw_rate,avg_match_secs,avg_overflow_secs,min_overflow_secs,max_overflow_secs,overflow_before_shrink_rate";
+
+const USAGE: &str = "usage: word-game simulate [--matches N] [--bots a,b,..] [--rules a,b,..] [--handicaps a,b] [--words NAME] [--seed N] [--out FILE]";
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{msg}\n{USAGE}"))
+}
+
+/// runs every pair of bot profiles against each other under every rule set, writing a csv report
+pub fn run_cli(args: &[String]) -> io::Result<()> {
+    let mut matches = 1000;
+    let mut bots: Vec<&BotProfile> = BOT_PROFILES.iter().collect();
+    let mut rule_sets = vec![GameRules::default()];
+    let mut handicaps = [Handicap::default(); 2];
+    let mut word_list = DEFAULT_WORD_LIST.to_owned();
+    let mut seed = rand::thread_rng().gen();
+    let mut out: Box<dyn Write> = Box::new(io::stdout());
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| invalid_input(format!("Missing value for {arg}")))?;
+
+        match arg.as_str() {
+            "--matches" => matches = value.parse().map_err(|_| invalid_input(format!("Invalid match count {value}")))?,
+            "--seed" => seed = value.parse().map_err(|_| invalid_input(format!("Invalid seed {value}")))?,
+            "--words" => word_list = value.clone(),
+            "--out" => out = Box::new(fs::File::create(value)?),
+            "--bots" => {
+                bots = value.split(',').map(|name| {
+                    BotProfile::find(name).ok_or_else(|| invalid_input(format!(
+                        "Unknown bot profile {name}. Available: {}",
+                        BOT_PROFILES.iter().map(|p| p.name).collect::<Vec<_>>().join(",")
+                    )))
+                }).collect::<io::Result<_>>()?;
+            },
+            "--handicaps" => {
+                let names: Vec<&str> = value.split(',').collect();
+                let [a, b] = names[..] else {
+                    return Err(invalid_input(format!("Expected two handicaps, got {value}")));
+                };
+
+                let find = |name: &str| HANDICAPS.iter().find(|h| h.name == name).copied().ok_or_else(|| invalid_input(format!(
+                    "Unknown handicap {name}. Available: {}",
+                    HANDICAPS.iter().map(|h| h.name).collect::<Vec<_>>().join(",")
+                )));
+                handicaps = [find(a)?, find(b)?];
+            },
+            "--rules" => {
+                rule_sets = value.split(',').map(|name| {
+                    GameRules::find(name).map_err(|e| invalid_input(format!("Couldn't load rules {name}: {e}")))
+                }).collect::<io::Result<_>>()?;
+            },
+            _ => return Err(invalid_input(format!("Unknown argument {arg}")))
+        }
+    }
+
+    let words = load_word_list(&word_list)?.words();
+    if rule_sets.iter().any(|rules| words.len() < rules.max_board_words * 2) {
+        return Err(invalid_input(format!("Word list {word_list} is too short to simulate with")));
+    }
+
+    info!("Simulating {} matches per matchup with seed {}", matches, seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    writeln!(out, "{CSV_HEADER}")?;
+
+    for rules in rule_sets.iter() {
+        for (i, a) in bots.iter().enumerate() {
+            for b in bots.iter().skip(i) {
+                let mut stats = MatchupStats::default();
+
+                for _ in 0..matches {
+                    stats.record(rules, simulate_match(rules, [a, b], handicaps, &words, &mut rng));
+                }
+
+                info!("Finished {} vs {} under {} rules", a.name, b.name, rules.name);
+                writeln!(out, "{}", stats.csv_row(rules, [a, b], handicaps))?;
+            }
+        }
+    }
+
+    out.flush()
+}
@@ -0,0 +1,73 @@
+//! The local table of best survival runs, kept in `scores/survival.txt` in the [`crate::user_data`] folder.
+
+use std::fmt;
+use std::io;
+
+use crate::user_data;
+
+pub const SURVIVAL_SCORES: &str = "scores/survival.txt";
+/// how many runs the table keeps
+pub const BEST_SCORES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurvivalScore {
+    /// seconds survived
+    pub time: f32,
+    pub wave: u32,
+    pub words: u64
+}
+
+impl SurvivalScore {
+    pub fn parse(line: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Invalid survival score '{line}'"));
+        let mut parts = line.split_whitespace();
+
+        let score = SurvivalScore {
+            time: parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?,
+            wave: parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?,
+            words: parts.next().and_then(|p| p.parse().ok()).ok_or_else(invalid)?
+        };
+
+        match parts.next() {
+            Some(_) => Err(invalid()),
+            None => Ok(score)
+        }
+    }
+}
+
+/// Format: `<secs> <wave> <words>`
+impl fmt::Display for SurvivalScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.time, self.wave, self.words)
+    }
+}
+
+/// the best runs so far, longest first. empty if nothing has been recorded yet
+pub fn load_best() -> io::Result<Vec<SurvivalScore>> {
+    let text = match user_data::read_to_string(SURVIVAL_SCORES) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e)
+    };
+
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(SurvivalScore::parse)
+        .collect()
+}
+
+/// adds `score` to the table, returning the new table and the place the run got in it, if any
+pub fn record(score: SurvivalScore) -> io::Result<(Vec<SurvivalScore>, Option<usize>)> {
+    let mut best = load_best().unwrap_or_else(|e| {
+        warn!("Replacing unreadable survival scores: {}", e);
+        vec![]
+    });
+
+    let place = best.iter().position(|b| score.time > b.time).unwrap_or(best.len());
+    best.insert(place, score);
+    best.truncate(BEST_SCORES);
+
+    user_data::write(SURVIVAL_SCORES, best.iter().map(|s| format!("{s}\n")).collect::<String>())?;
+
+    Ok((best, (place < BEST_SCORES).then_some(place)))
+}
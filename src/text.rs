@@ -0,0 +1,135 @@
+//! Unicode aware helpers for comparing and editing words, so lists in any language work the same as english ones.
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// the form two words are compared in: composed, lowercased, and with ß and final sigma folded like their
+/// other forms. with `ignore_accents`, diacritics are dropped too, so "é" matches "e" and "й" matches "и"
+pub fn fold(text: &str, ignore_accents: bool) -> String {
+    let lower: String = text.nfc().collect::<String>().to_lowercase()
+        .chars()
+        .flat_map(|c| match c {
+            'ß' | 'ẞ' => vec!['s', 's'],
+            'ς' => vec!['σ'],
+            c => vec![c]
+        })
+        .collect();
+
+    if ignore_accents {
+        lower.nfd().filter(|c| !is_combining_mark(*c)).nfc().collect()
+    } else {
+        lower
+    }
+}
+
+/// whether `a` and `b` are the same word once folded
+pub fn same_word(a: &str, b: &str, ignore_accents: bool) -> bool {
+    fold(a, ignore_accents) == fold(b, ignore_accents)
+}
+
+/// how many characters at the start of `word` have been typed, if `typed` is the start of it.
+/// the prefix always covers whole graphemes, so a letter isn't split from its accent
+pub fn typed_prefix(word: &str, typed: &str, ignore_accents: bool) -> Option<usize> {
+    let typed = fold(typed, ignore_accents);
+    if typed.is_empty() {
+        return None;
+    }
+
+    let mut folded = String::new();
+    let mut chars = 0;
+    for grapheme in word.graphemes(true) {
+        if folded.len() >= typed.len() {
+            break;
+        }
+
+        folded.push_str(&fold(grapheme, ignore_accents));
+        chars += grapheme.chars().count();
+    }
+
+    folded.starts_with(&typed).then_some(chars)
+}
+
+/// `word` with its first letter uppercase and the rest lowercase
+pub fn capitalize(word: &str) -> String {
+    let mut graphemes = word.nfc().collect::<String>();
+    let rest = graphemes.split_off(graphemes.graphemes(true).next().map_or(0, str::len));
+
+    format!("{}{}", graphemes.to_uppercase(), rest.to_lowercase())
+}
+
+/// removes the last grapheme of `text`, so backspace deletes an accented letter in one go
+pub fn pop_grapheme(text: &mut String) {
+    let len = text.graphemes(true).next_back().map_or(0, str::len);
+    text.truncate(text.len() - len);
+}
+
+/// whether `c` can be part of a typed word. combining accents are allowed for keyboards that send them separately
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphabetic() || c == ' ' || is_combining_mark(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accents_fold_only_when_ignored() {
+        assert_eq!(fold("Café", false), "café");
+        assert_eq!(fold("Café", true), "cafe");
+        // decomposed and composed accents are the same letter
+        assert!(same_word("cafe\u{301}", "café", false));
+        assert!(!same_word("café", "cafe", false));
+        assert!(same_word("café", "CAFE", true));
+        assert!(same_word("йод", "иод", true));
+    }
+
+    #[test]
+    fn sharp_s_and_final_sigma() {
+        assert!(same_word("Straße", "STRASSE", false));
+        assert!(same_word("ΟΔΟΣ", "οδος", false));
+
+        // ß can't be half typed, so it's crossed out as soon as its first s is
+        assert_eq!(typed_prefix("Straße", "stra", false), Some(4));
+        assert_eq!(typed_prefix("Straße", "stras", false), Some(5));
+        assert_eq!(typed_prefix("Straße", "strass", false), Some(5));
+        assert_eq!(typed_prefix("Straße", "strasse", false), Some(6));
+        assert_eq!(typed_prefix("Straße", "strasx", false), None);
+    }
+
+    #[test]
+    fn typed_prefix_counts_chars_of_the_word() {
+        assert_eq!(typed_prefix("Apple", "", false), None);
+        assert_eq!(typed_prefix("Apple", "app", false), Some(3));
+        assert_eq!(typed_prefix("Apple", "apples", false), None);
+        assert_eq!(typed_prefix("Apple", "apx", false), None);
+
+        assert_eq!(typed_prefix("Café", "cafe", false), None);
+        assert_eq!(typed_prefix("Café", "café", false), Some(4));
+        assert_eq!(typed_prefix("Café", "cafe", true), Some(4));
+        // a decomposed é is two chars of the word, and both are crossed out
+        assert_eq!(typed_prefix("Cafe\u{301}", "café", false), Some(5));
+        assert_eq!(typed_prefix("Cafe\u{301}", "caf", false), Some(3));
+    }
+
+    #[test]
+    fn pop_whole_graphemes() {
+        let mut text = "cafe\u{301}".to_owned();
+        pop_grapheme(&mut text);
+        assert_eq!(text, "caf");
+
+        let mut text = "né".to_owned();
+        pop_grapheme(&mut text);
+        assert_eq!(text, "n");
+        pop_grapheme(&mut text);
+        pop_grapheme(&mut text);
+        assert_eq!(text, "");
+    }
+
+    #[test]
+    fn capitalize_first_grapheme() {
+        assert_eq!(capitalize("éCOLE"), "École");
+        assert_eq!(capitalize("e\u{301}cole"), "École");
+        assert_eq!(capitalize(""), "");
+    }
+}
@@ -0,0 +1,704 @@
+//! Tournaments of 4 to 32 players. The host doesn't play: it pairs players up for every round and relays their
+//! matches, playing the host's side of the match settings handshake with each of them, so a match between two
+//! players works exactly like a normal online match. Results are taken from how each match ends.
+//!
+//! Players join by connecting and sending [`Packet::Register`], and are sent [`Packet::Bracket`] whenever the
+//! bracket changes while they aren't playing. Every match is played with the host's word list.
+
+use std::fmt;
+use std::io;
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use crate::network::{check_version, Connection, Packet};
+use crate::rules::{GameRules, Handicap};
+use crate::word_game::{GameMode, GameOutcome, Score, ScoreBy};
+use crate::word_list::WordList;
+
+pub const MIN_PLAYERS: usize = 4;
+pub const MAX_PLAYERS: usize = 32;
+/// not the versus port, so a tournament can be hosted while a match or a bot runs on the same machine
+pub const TOURNAMENT_PORT: u16 = 5556;
+/// how long the bracket is shown before the next round starts
+const ROUND_BREAK: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    SingleElimination,
+    /// players are out after their second loss. the winner of the losers' side has to beat the winner of the
+    /// winners' side twice in the final
+    DoubleElimination,
+    /// everyone plays every round against someone with a similar record, for enough rounds to find a winner
+    Swiss
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::SingleElimination, Format::DoubleElimination, Format::Swiss];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::SingleElimination => "single elimination",
+            Format::DoubleElimination => "double elimination",
+            Format::Swiss => "swiss"
+        }
+    }
+
+    /// how many losses knock a player out
+    fn max_losses(self) -> Option<u32> {
+        match self {
+            Format::SingleElimination => Some(1),
+            Format::DoubleElimination => Some(2),
+            Format::Swiss => None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entrant {
+    pub name: String,
+    pub handicap: Handicap,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub byes: u32
+}
+
+impl Entrant {
+    pub fn new(name: String, handicap: Handicap) -> Self {
+        Entrant { name, handicap, wins: 0, losses: 0, draws: 0, byes: 0 }
+    }
+
+    /// swiss points, doubled so that draws are worth a whole point: 2 for a win or a bye, 1 for a draw
+    pub fn points(&self) -> u32 {
+        2 * (self.wins + self.byes) + self.draws
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pairing {
+    pub round: u32,
+    /// indices into [`Tournament::players`]. the second player is None for a bye
+    pub players: (usize, Option<usize>),
+    /// the outcome for the first player, once the match is over
+    pub outcome: Option<GameOutcome>
+}
+
+#[derive(Debug)]
+pub struct Tournament {
+    pub format: Format,
+    /// in seeding order
+    pub players: Vec<Entrant>,
+    /// the current round, starting at 1. 0 before the first round
+    pub round: u32,
+    pub pairings: Vec<Pairing>,
+    swiss_rounds: u32
+}
+
+impl Tournament {
+    pub fn new(format: Format, players: Vec<Entrant>) -> Self {
+        let swiss_rounds = players.len().next_power_of_two().trailing_zeros().max(1);
+        Tournament { format, players, round: 0, pairings: vec![], swiss_rounds }
+    }
+
+    pub fn is_eliminated(&self, player: usize) -> bool {
+        self.format.max_losses().is_some_and(|max| self.players[player].losses >= max)
+    }
+
+    fn alive(&self) -> Vec<usize> {
+        (0..self.players.len()).filter(|p| !self.is_eliminated(*p)).collect()
+    }
+
+    /// indices of the pairings of the current round
+    pub fn current_round(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.pairings.len()).filter(|i| self.pairings[*i].round == self.round)
+    }
+
+    pub fn round_done(&self) -> bool {
+        self.current_round().all(|i| self.pairings[i].outcome.is_some())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        if !self.round_done() {
+            return false;
+        }
+
+        match self.format {
+            Format::Swiss => self.round >= self.swiss_rounds,
+            _ => self.alive().len() <= 1
+        }
+    }
+
+    /// players from best to worst
+    pub fn standings(&self) -> Vec<usize> {
+        let mut standings: Vec<usize> = (0..self.players.len()).collect();
+        standings.sort_by_key(|p| {
+            let player = &self.players[*p];
+            (self.is_eliminated(*p), std::cmp::Reverse(player.points()), player.losses)
+        });
+        standings
+    }
+
+    pub fn winner(&self) -> Option<usize> {
+        self.is_finished().then(|| self.standings()[0])
+    }
+
+    fn have_played(&self, a: usize, b: usize) -> bool {
+        self.pairings.iter().any(|p| p.players == (a, Some(b)) || p.players == (b, Some(a)))
+    }
+
+    /// pairs up `players` so that nobody plays someone they've played before, each with the best ranked opponent
+    /// that still allows it. None if there's no way to
+    fn pair_new_opponents(&self, players: &[usize]) -> Option<Vec<(usize, usize)>> {
+        let Some((player, rest)) = players.split_first() else {
+            return Some(vec![]);
+        };
+
+        rest.iter().filter(|opponent| !self.have_played(*player, **opponent)).find_map(|opponent| {
+            let others: Vec<usize> = rest.iter().copied().filter(|p| p != opponent).collect();
+            let mut pairs = self.pair_new_opponents(&others)?;
+            pairs.insert(0, (*player, *opponent));
+            Some(pairs)
+        })
+    }
+
+    /// pairs `players` in order, giving the first one a bye if there's an odd number of them
+    fn pair_in_order(&mut self, players: &[usize]) {
+        let (bye, rest) = match players.len() % 2 {
+            1 => (Some(players[0]), &players[1..]),
+            _ => (None, players)
+        };
+
+        if let Some(bye) = bye {
+            self.add_bye(bye);
+        }
+
+        for pair in rest.chunks(2) {
+            self.pairings.push(Pairing { round: self.round, players: (pair[0], Some(pair[1])), outcome: None });
+        }
+    }
+
+    fn add_bye(&mut self, player: usize) {
+        self.players[player].byes += 1;
+        self.pairings.push(Pairing { round: self.round, players: (player, None), outcome: Some(GameOutcome::Win) });
+    }
+
+    /// pairs up the players for the next round
+    pub fn next_round(&mut self) {
+        self.round += 1;
+
+        match self.format {
+            Format::SingleElimination => self.pair_in_order(&self.alive()),
+            Format::DoubleElimination => {
+                let (winners, losers): (Vec<usize>, Vec<usize>) = self.alive().into_iter().partition(|p| self.players[*p].losses == 0);
+
+                if winners.len() == 1 && losers.len() == 1 {
+                    // the final. if the winners' side loses, both have lost once and they play again
+                    self.pair_in_order(&[winners[0], losers[0]]);
+                } else {
+                    self.pair_in_order(&winners);
+                    self.pair_in_order(&losers);
+                }
+            },
+            Format::Swiss => {
+                let standings = self.standings();
+
+                // the lowest ranked player who has had the fewest byes sits out, unless that leaves the others
+                // without new opponents
+                let mut byes: Vec<Option<usize>> = match standings.len() % 2 {
+                    1 => standings.iter().rev().map(|p| Some(*p)).collect(),
+                    _ => vec![None]
+                };
+                byes.sort_by_key(|bye| bye.map(|p| self.players[p].byes));
+
+                let paired = byes.iter().find_map(|bye| {
+                    let rest: Vec<usize> = standings.iter().copied().filter(|p| Some(*p) != *bye).collect();
+                    self.pair_new_opponents(&rest).map(|pairs| (*bye, pairs))
+                });
+
+                // once everyone has played everyone, opponents have to repeat
+                let (bye, pairs) = paired.unwrap_or_else(|| {
+                    let bye = byes[0];
+                    let rest: Vec<usize> = standings.iter().copied().filter(|p| Some(*p) != bye).collect();
+                    (bye, rest.chunks(2).map(|pair| (pair[0], pair[1])).collect())
+                });
+
+                if let Some(bye) = bye {
+                    self.add_bye(bye);
+                }
+
+                for (a, b) in pairs {
+                    self.pairings.push(Pairing { round: self.round, players: (a, Some(b)), outcome: None });
+                }
+            }
+        }
+    }
+
+    /// records the outcome of a match for its first player. draws are replayed in elimination formats
+    pub fn record(&mut self, pairing: usize, outcome: GameOutcome) {
+        let Pairing { players: (a, Some(b)), .. } = self.pairings[pairing] else {
+            return;
+        };
+        self.pairings[pairing].outcome = Some(outcome);
+
+        match outcome {
+            GameOutcome::Win => {
+                self.players[a].wins += 1;
+                self.players[b].losses += 1;
+            },
+            GameOutcome::Loss => {
+                self.players[a].losses += 1;
+                self.players[b].wins += 1;
+            },
+            GameOutcome::Draw => {
+                self.players[a].draws += 1;
+                self.players[b].draws += 1;
+
+                if self.format.max_losses().is_some() {
+                    self.pairings.push(Pairing { round: self.round, players: (a, Some(b)), outcome: None });
+                }
+            }
+        }
+    }
+
+    fn record_text(&self, player: usize) -> String {
+        let p = &self.players[player];
+        match self.format {
+            Format::Swiss => format!("{} ({}pts)", p.name, p.points() as f32 / 2.0),
+            _ => format!("{} ({}-{})", p.name, p.wins, p.losses)
+        }
+    }
+}
+
+/// the current and previous rounds, then the standings
+impl fmt::Display for Tournament {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}, round {}", self.format.label(), self.round)?;
+
+        for round in self.round.saturating_sub(1).max(1)..=self.round {
+            writeln!(f, "round {round}")?;
+
+            for pairing in self.pairings.iter().filter(|p| p.round == round) {
+                let a = &self.players[pairing.players.0].name;
+                match (pairing.players.1.map(|b| &self.players[b].name), pairing.outcome) {
+                    (None, _) => writeln!(f, "  {a} has a bye")?,
+                    (Some(b), None) => writeln!(f, "  {a} v {b}")?,
+                    (Some(b), Some(GameOutcome::Win)) => writeln!(f, "  {a} beat {b}")?,
+                    (Some(b), Some(GameOutcome::Loss)) => writeln!(f, "  {b} beat {a}")?,
+                    (Some(b), Some(GameOutcome::Draw)) => writeln!(f, "  {a} drew with {b}")?
+                }
+            }
+        }
+
+        match self.winner() {
+            Some(winner) => writeln!(f, "{} wins the tournament!", self.players[winner].name)?,
+            None if self.format == Format::Swiss => writeln!(f, "standings")?,
+            None => writeln!(f, "still in")?
+        }
+
+        for row in self.standings().chunks(4) {
+            let row: Vec<String> = row.iter()
+                .filter(|p| !self.is_eliminated(**p) || self.format == Format::Swiss)
+                .map(|p| self.record_text(*p))
+                .collect();
+
+            if !row.is_empty() {
+                writeln!(f, "  {}", row.join("  "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// a match being played between two players, relayed through the host
+#[derive(Debug)]
+struct LiveMatch {
+    pairing: usize,
+    /// final time attack scores, once each player's time is up
+    scores: (Option<Score>, Option<Score>)
+}
+
+#[derive(Debug)]
+pub struct TournamentHost {
+    listener: TcpListener,
+    /// connections that haven't registered yet, and whether they sent a matching version
+    pending: Vec<(Connection, bool)>,
+    /// the connection of every registered player, in the same order as the entrants. None once they left
+    connections: Vec<Option<Connection>>,
+    pub entrants: Vec<Entrant>,
+    pub format: Format,
+    pub rules: GameRules,
+    /// the word list every match is played with, and its name
+    pub word_list: (String, WordList),
+    pub tournament: Option<Tournament>,
+    live: Vec<LiveMatch>,
+    next_round_at: Option<Instant>
+}
+
+impl TournamentHost {
+    pub fn bind(port: u16, rules: GameRules, word_list: (String, WordList)) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        info!("Hosting a tournament on port {}", port);
+
+        Ok(TournamentHost {
+            listener,
+            pending: vec![],
+            connections: vec![],
+            entrants: vec![],
+            format: Format::SingleElimination,
+            rules,
+            word_list,
+            tournament: None,
+            live: vec![],
+            next_round_at: None
+        })
+    }
+
+    pub fn can_start(&self) -> bool {
+        self.tournament.is_none() && self.entrants.len() >= MIN_PLAYERS
+    }
+
+    pub fn start(&mut self) -> io::Result<()> {
+        if !self.can_start() {
+            return Ok(());
+        }
+
+        info!("Starting a {} tournament with {} players", self.format.label(), self.entrants.len());
+        self.tournament = Some(Tournament::new(self.format, self.entrants.clone()));
+        self.start_round()
+    }
+
+    /// seconds until the next round starts, during the break between rounds
+    pub fn break_left(&self) -> Option<f32> {
+        self.next_round_at.map(|at| at.saturating_duration_since(Instant::now()).as_secs_f32())
+    }
+
+    /// accepts players before the tournament starts and relays matches once it has
+    pub fn poll(&mut self) -> io::Result<()> {
+        if self.tournament.is_none() {
+            return self.poll_registrations();
+        }
+
+        if self.next_round_at.is_some_and(|at| Instant::now() >= at) {
+            self.next_round_at = None;
+            self.start_round()?;
+        }
+
+        for i in (0..self.live.len()).rev() {
+            if let Some(outcome) = self.poll_match(i) {
+                let pairing = self.live.remove(i).pairing;
+                self.finish_match(pairing, outcome)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn poll_registrations(&mut self) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    info!("Got connection from {}", addr);
+                    self.pending.push((Connection::new(stream)?, false));
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e)
+            }
+        }
+
+        for (mut conn, checked) in std::mem::take(&mut self.pending) {
+            match conn.poll_next_packet() {
+                Ok(None) => self.pending.push((conn, checked)),
+                // answered either way so that the player sees the mismatch too
+                Ok(Some(Packet::ClientInfo { major, minor })) if !checked => {
+                    match conn.send_packet(Packet::client_info()).and_then(|_| check_version(major, minor)) {
+                        Ok(()) => self.pending.push((conn, true)),
+                        Err(e) => warn!("Turning a player away: {}", e)
+                    }
+                },
+                Ok(Some(Packet::Register { name, .. })) if !checked => warn!("Turning {} away, they didn't send their version", name),
+                Ok(Some(Packet::Register { name, handicap })) if self.entrants.len() < MAX_PLAYERS => {
+                    info!("{} joined the tournament", name);
+                    self.entrants.push(Entrant::new(name, handicap));
+                    self.connections.push(Some(conn));
+                    self.broadcast_bracket();
+                },
+                Ok(Some(Packet::Register { name, .. })) => warn!("Turning {} away, the tournament is full", name),
+                Ok(Some(p)) => warn!("Expected a registration, received {:?}", p),
+                Err(e) => warn!("Dropping unregistered connection: {}", e)
+            }
+        }
+
+        Ok(())
+    }
+
+    fn start_round(&mut self) -> io::Result<()> {
+        let tournament = self.tournament.as_mut().unwrap();
+        tournament.next_round();
+        info!("Starting round {}", tournament.round);
+
+        let pairings: Vec<usize> = tournament.current_round().collect();
+        for pairing in pairings {
+            self.start_match(pairing)?;
+        }
+
+        self.broadcast_bracket();
+        Ok(())
+    }
+
+    fn start_match(&mut self, pairing: usize) -> io::Result<()> {
+        let tournament = self.tournament.as_ref().unwrap();
+        let (a, b) = match tournament.pairings[pairing].players {
+            (a, Some(b)) => (a, b),
+            (_, None) => return Ok(())
+        };
+
+        // players who left forfeit
+        match (self.connections[a].is_some(), self.connections[b].is_some()) {
+            (true, true) => {},
+            (a_here, _) => {
+                let outcome = if a_here { GameOutcome::Win } else { GameOutcome::Loss };
+                return self.finish_match(pairing, outcome);
+            }
+        }
+
+        for (player, opponent) in [(a, b), (b, a)] {
+            let handicap = tournament.players[opponent].handicap;
+            let conn = self.connections[player].as_mut().unwrap();
+            conn.send_packet(Packet::MatchSettings { rules: self.rules.clone() })?;
+            conn.send_packet(Packet::Handicap { handicap })?;
+            conn.send_packet(Packet::WordListHash { name: self.word_list.0.clone(), hash: self.word_list.1.hash() })?;
+        }
+
+        self.live.push(LiveMatch { pairing, scores: (None, None) });
+        Ok(())
+    }
+
+    /// relays the packets of a live match, returning the outcome for its first player once it's over
+    fn poll_match(&mut self, live: usize) -> Option<GameOutcome> {
+        let pairing = self.tournament.as_ref().unwrap().pairings[self.live[live].pairing];
+        let (a, b) = (pairing.players.0, pairing.players.1.unwrap());
+
+        for (player, opponent, first) in [(a, b, true), (b, a, false)] {
+            loop {
+                let packet = match self.connections[player].as_mut().map(Connection::poll_next_packet) {
+                    Some(Ok(Some(packet))) => packet,
+                    Some(Ok(None)) | None => break,
+                    Some(Err(e)) => {
+                        warn!("{} left the tournament: {}", self.entrants[player].name, e);
+                        self.connections[player] = None;
+                        self.relay(opponent, Packet::ILost {});
+                        return Some(if first { GameOutcome::Loss } else { GameOutcome::Win });
+                    }
+                };
+
+                match packet {
+                    Packet::ILost {} => {
+                        self.relay(opponent, Packet::ILost {});
+                        return Some(if first { GameOutcome::Loss } else { GameOutcome::Win });
+                    },
+                    Packet::Score { score, done } => {
+                        if done {
+                            let scores = &mut self.live[live].scores;
+                            *(if first { &mut scores.0 } else { &mut scores.1 }) = Some(score);
+                        }
+                        self.relay(opponent, Packet::Score { score, done });
+                    },
+                    p @ (Packet::AddWord { .. } | Packet::PowerUp { .. }) => self.relay(opponent, p),
+                    Packet::WordListRequest => {
+                        let (name, list) = self.word_list.clone();
+                        self.relay(player, Packet::WordList { name, list });
+                    },
+                    // the player's side of the handshake, and restarts, which the host decides
+                    Packet::Handicap { .. } | Packet::WaitingToRestart => {},
+                    p => warn!("Unexpected packet {:?} from {}", p, self.entrants[player].name)
+                }
+            }
+        }
+
+        match self.live[live].scores {
+            (Some(mine), Some(theirs)) => {
+                let by = match self.rules.mode() {
+                    GameMode::TimeAttack { score_by, .. } => score_by,
+                    _ => ScoreBy::Words
+                };
+
+                Some(match mine.get(by).cmp(&theirs.get(by)) {
+                    std::cmp::Ordering::Greater => GameOutcome::Win,
+                    std::cmp::Ordering::Less => GameOutcome::Loss,
+                    std::cmp::Ordering::Equal => GameOutcome::Draw
+                })
+            },
+            _ => None
+        }
+    }
+
+    fn relay(&mut self, player: usize, packet: Packet) {
+        if let Some(conn) = &mut self.connections[player] {
+            if let Err(e) = conn.send_packet(packet) {
+                warn!("Failed to relay to {}: {}", self.entrants[player].name, e);
+                self.connections[player] = None;
+            }
+        }
+    }
+
+    fn finish_match(&mut self, pairing: usize, outcome: GameOutcome) -> io::Result<()> {
+        let tournament = self.tournament.as_mut().unwrap();
+        tournament.record(pairing, outcome);
+
+        // a drawn elimination match is replayed straight away
+        let replay = tournament.current_round().find(|i| *i > pairing && tournament.pairings[*i].outcome.is_none()
+            && tournament.pairings[*i].players == tournament.pairings[pairing].players);
+
+        self.broadcast_bracket();
+
+        if let Some(replay) = replay {
+            return self.start_match(replay);
+        }
+
+        let tournament = self.tournament.as_ref().unwrap();
+        if let Some(winner) = tournament.winner() {
+            info!("{} won the tournament", tournament.players[winner].name);
+        } else if tournament.round_done() {
+            self.next_round_at = Some(Instant::now() + ROUND_BREAK);
+        }
+
+        Ok(())
+    }
+
+    /// what players see between matches
+    pub fn bracket_text(&self) -> String {
+        match &self.tournament {
+            Some(tournament) => tournament.to_string(),
+            None => format!(
+                "waiting for players ({}/{})\n{}",
+                self.entrants.len(),
+                MAX_PLAYERS,
+                self.entrants.iter().map(|e| e.name.as_str()).collect::<Vec<_>>().join(", ")
+            )
+        }
+    }
+
+    /// sends the bracket to everyone who isn't in a match
+    fn broadcast_bracket(&mut self) {
+        let text = self.bracket_text();
+
+        for player in 0..self.connections.len() {
+            let playing = self.live.iter().any(|m| {
+                let players = self.tournament.as_ref().unwrap().pairings[m.pairing].players;
+                players.0 == player || players.1 == Some(player)
+            });
+
+            if !playing {
+                self.relay(player, Packet::Bracket { text: text.clone() });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tournament(format: Format, players: usize) -> Tournament {
+        let players = (0..players).map(|i| Entrant::new(format!("p{i}"), Handicap::default())).collect();
+        Tournament::new(format, players)
+    }
+
+    /// the players of every pairing of the current round, byes included
+    fn round(tournament: &Tournament) -> Vec<(usize, Option<usize>)> {
+        tournament.current_round().map(|i| tournament.pairings[i].players).collect()
+    }
+
+    /// plays a round, the outcome for the first player of each match being picked by `outcome`
+    fn play(tournament: &mut Tournament, outcome: impl Fn(usize, usize) -> GameOutcome) {
+        tournament.next_round();
+
+        let matches: Vec<usize> = tournament.current_round().filter(|i| tournament.pairings[*i].outcome.is_none()).collect();
+        for i in matches {
+            let (a, Some(b)) = tournament.pairings[i].players else { unreachable!() };
+            tournament.record(i, outcome(a, b));
+        }
+    }
+
+    #[test]
+    fn single_elimination_with_byes() {
+        let mut t = tournament(Format::SingleElimination, 5);
+        let first_wins = |_, _| GameOutcome::Win;
+
+        play(&mut t, first_wins);
+        assert_eq!(round(&t), vec![(0, None), (1, Some(2)), (3, Some(4))]);
+        assert!(!t.is_finished());
+
+        play(&mut t, first_wins);
+        assert_eq!(round(&t), vec![(0, None), (1, Some(3))]);
+
+        play(&mut t, first_wins);
+        assert_eq!(round(&t), vec![(0, Some(1))]);
+        assert_eq!(t.winner(), Some(0));
+        assert_eq!((0..5).filter(|p| t.is_eliminated(*p)).count(), 4);
+    }
+
+    #[test]
+    fn elimination_draws_are_replayed() {
+        let mut t = tournament(Format::SingleElimination, 4);
+
+        t.next_round();
+        t.record(0, GameOutcome::Draw);
+        assert!(!t.round_done());
+        assert_eq!(round(&t), vec![(0, Some(1)), (2, Some(3)), (0, Some(1))]);
+    }
+
+    #[test]
+    fn double_elimination_losers_bracket() {
+        let mut t = tournament(Format::DoubleElimination, 4);
+
+        play(&mut t, |_, _| GameOutcome::Win);
+        assert_eq!(round(&t), vec![(0, Some(1)), (2, Some(3))]);
+
+        // the winners play each other, and so do the losers
+        play(&mut t, |a, _| if a == 0 { GameOutcome::Win } else { GameOutcome::Loss });
+        assert_eq!(round(&t), vec![(0, Some(2)), (1, Some(3))]);
+        assert!(t.is_eliminated(1));
+
+        // the unbeaten player waits for the losers' side to finish
+        play(&mut t, |_, _| GameOutcome::Win);
+        assert_eq!(round(&t), vec![(0, None), (2, Some(3))]);
+        assert!(t.is_eliminated(3));
+
+        // the winner of the losers' side beats the unbeaten player, so they play again
+        play(&mut t, |_, _| GameOutcome::Loss);
+        assert_eq!(round(&t), vec![(0, Some(2))]);
+        assert!(!t.is_finished());
+
+        play(&mut t, |_, _| GameOutcome::Loss);
+        assert_eq!(round(&t), vec![(0, Some(2))]);
+        assert_eq!(t.winner(), Some(2));
+    }
+
+    #[test]
+    fn swiss_pairings_dont_repeat() {
+        for players in MIN_PLAYERS..=12 {
+            let mut t = tournament(Format::Swiss, players);
+
+            while !t.is_finished() {
+                play(&mut t, |a, b| if (a + b) % 3 == 0 { GameOutcome::Loss } else { GameOutcome::Win });
+
+                let mut seen: Vec<usize> = round(&t).iter().flat_map(|(a, b)| [Some(*a), *b]).flatten().collect();
+                seen.sort();
+                assert_eq!(seen, (0..players).collect::<Vec<_>>(), "everyone plays once a round");
+            }
+
+            let matches: Vec<(usize, usize)> = t.pairings.iter()
+                .filter_map(|p| p.players.1.map(|b| (p.players.0.min(b), p.players.0.max(b))))
+                .collect();
+            for (i, pair) in matches.iter().enumerate() {
+                assert!(!matches[..i].contains(pair), "{players} players: {pair:?} played twice");
+            }
+
+            let byes: Vec<u32> = t.players.iter().map(|p| p.byes).collect();
+            assert!(byes.iter().all(|b| *b <= 1), "{players} players: byes {byes:?}");
+        }
+    }
+}
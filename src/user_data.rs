@@ -0,0 +1,37 @@
+//! Where the game keeps what it saves for the player: ghost runs, score tables, keystroke stats and their own word
+//! lists all go in the `word-game` folder of the user's config directory (`$XDG_CONFIG_HOME`, `~/.config` or
+//! `%APPDATA%`), or in the working directory if they don't have one.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "word-game";
+
+/// the game's folder in the user's config directory, if there is one
+pub fn dir() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from));
+
+    config.map(|c| c.join(APP_DIR))
+}
+
+/// where the file `name` is kept
+pub fn path(name: impl AsRef<Path>) -> PathBuf {
+    dir().unwrap_or_default().join(name)
+}
+
+pub fn read_to_string(name: impl AsRef<Path>) -> io::Result<String> {
+    fs::read_to_string(path(name))
+}
+
+/// writes the file `name`, creating the folders it's in
+pub fn write(name: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, contents)
+}
@@ -1,50 +1,254 @@
-use std::{time::Instant, path::Path, str::Lines, net::{TcpListener, TcpStream}};
+use std::{time::{Duration, Instant, SystemTime}, path::{Path, PathBuf}, net::{TcpListener, TcpStream}};
 
 use ggez::{Context, graphics::{FontData, Rect}, GameResult};
-use rand::Rng;
+use rand::{Rng, SeedableRng, rngs::StdRng};
 
-use crate::network::{Packet, Connection, connect_to_dummy};
+use crate::network::{Packet, Connection, check_version, connect_to_dummy, connect_to_ghost};
+use crate::ghost::{GhostRun, GhostEventKind};
+use crate::powerup::PowerUp;
+use crate::survival::{self, SurvivalScore};
+use crate::daily::{self, DailyScore};
+use crate::tournament::{TournamentHost, TOURNAMENT_PORT};
+use crate::word_list::{self, WordList};
+use crate::keystrokes::{self, KeyStats, MAX_KEY_SECS};
+use crate::handshake::Handshake;
+use crate::user_data;
+use crate::board::Board;
+use crate::bot_host::connect_to_external_bot;
+use crate::rules::{GameRules, Handicap, WordDifficulty, HANDICAPS};
+use crate::text::{capitalize, typed_prefix};
+
+use serde::{Deserialize, Serialize};
 
 pub const DEFAULT_WORD_LIST: &str = "5000_out";
+/// how many random words weighted selection picks from
+const WEIGHTED_CANDIDATES: usize = 20;
+/// how often the word list file is checked for changes
+pub const WORD_LIST_RELOAD_CHECK: Duration = Duration::from_secs(1);
+/// the folder of [`user_data`] where a list copied in is played with straight away
+pub const DROP_DIR: &str = "drop";
 
 #[derive(Debug, Clone, Copy)]
 pub enum GameOutcome {
-    Win, Loss
+    Win, Loss, Draw
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreBy {
+    Words, Characters
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub words: u32,
+    pub chars: u32
+}
+
+impl Score {
+    pub fn add_word(&mut self, word: &str) {
+        self.words += 1;
+        self.chars += word.chars().count() as u32;
+    }
+
+    pub fn get(&self, by: ScoreBy) -> u32 {
+        match by {
+            ScoreBy::Words => self.words,
+            ScoreBy::Characters => self.chars
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    /// against an opponent over a [`Connection`], until someone overflows their received words
+    Versus,
+    /// against an opponent for a fixed amount of seconds, whoever cleared the most wins
+    TimeAttack { duration: u64, score_by: ScoreBy },
+    /// alone, with no connection, for a fixed amount of seconds
+    Practice { duration: u64 },
+    /// alone against waves of words that keep getting faster, until you overflow your received words
+    Survival,
+    /// survival with the words and waves of the day's seed, see [`crate::daily`]
+    Daily { day: u64 }
+}
+
+impl GameMode {
+    pub fn label(&self) -> String {
+        match self {
+            GameMode::Versus => "versus".to_owned(),
+            GameMode::TimeAttack { duration, score_by: ScoreBy::Words } => format!("time attack {duration}s, words"),
+            GameMode::TimeAttack { duration, score_by: ScoreBy::Characters } => format!("time attack {duration}s, chars"),
+            GameMode::Practice { duration } => format!("practice {duration}s"),
+            GameMode::Survival => "survival".to_owned(),
+            GameMode::Daily { day } => format!("daily {}", daily::date(*day))
+        }
+    }
+
+    /// whether waves of words are sent at the player instead of an opponent
+    pub fn has_waves(&self) -> bool {
+        matches!(self, GameMode::Survival | GameMode::Daily { .. })
+    }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum MenuOption {
+    Versus,
+    Practice(u64),
+    Survival,
+    Daily,
+    HostTournament,
+    JoinTournament,
+    /// cycles through the word lists
+    WordList
+}
+
+impl MenuOption {
+    pub fn label(&self) -> String {
+        match self {
+            MenuOption::Versus => "Versus".to_owned(),
+            MenuOption::Practice(secs) => format!("Practice {secs}s"),
+            MenuOption::Survival => "Survival".to_owned(),
+            MenuOption::Daily => format!("Daily {}", daily::date(daily::today())),
+            MenuOption::HostTournament => "Host tournament".to_owned(),
+            MenuOption::JoinTournament => "Join tournament".to_owned(),
+            MenuOption::WordList => "Words".to_owned()
+        }
+    }
+}
+
+pub const MENU_OPTIONS: &[MenuOption] = &[
+    MenuOption::Versus,
+    MenuOption::Practice(30),
+    MenuOption::Practice(60),
+    MenuOption::Practice(120),
+    MenuOption::Survival,
+    MenuOption::Daily,
+    MenuOption::HostTournament,
+    MenuOption::JoinTournament,
+    MenuOption::WordList
+];
+
 #[derive(Debug)]
 pub struct OngoingGame {
+    pub mode: GameMode,
+    pub rules: GameRules,
+    pub handicap: Handicap,
+    pub opponent_handicap: Handicap,
     pub start_time: Instant,
     pub total_words: u64,
     
-    pub current_words: Vec<String>,
-    pub received_words: Vec<String>,
-
-    pub last_new_word: Instant,
+    /// what goes on the board instead of the word list, like quotes
+    pub texts: Option<Vec<String>>,
+    pub board: Board,
     pub current_text: String,
+    /// seconds into the game as of this frame, so that everything in a frame happens at the same time
+    clock: f32,
+
+    /// picks the words put on your board, seeded by the day in the daily challenge
+    board_rng: StdRng,
+    /// picks the words sent by survival waves
+    wave_rng: StdRng,
+    /// picks garbage, bonus and split attack words and shuffles boards, so a seeded game plays the same for the
+    /// same typing
+    play_rng: StdRng,
+    /// seconds into the game at which the next survival wave word is sent
+    pub next_wave_word: f32,
+
+    /// every character typed, including ones later deleted
+    pub typed_chars: u64,
+    /// seconds into the game of the last character typed
+    last_keystroke: Option<f32>,
+    /// the keystrokes of this game, added to the saved stats when it ends
+    pub keystrokes: KeyStats,
+    /// the saved stats that words are picked from, with adaptive rules
+    adaptive: Option<KeyStats>,
+    /// characters of submissions that cleared at least one word
+    pub correct_chars: u64,
 
-    pub conn: Connection
+    pub score: Score,
+    pub opponent_score: Score,
+    /// whether the final score has been sent to the opponent at the end of a time attack
+    pub done: bool,
+    pub opponent_done: bool,
+
+    pub ghost: GhostRun,
+
+    /// None in single player modes
+    pub conn: Option<Connection>
 }
 
+// there is only ever one state, so the size of the ongoing game doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum GameState {
     //InterState is an invalid state. It is needed to be able to move values out of one state (with std::mem::take) to put them into the new state
     InvalidState,
     Ongoing(OngoingGame),
     Ended {
+        rules: GameRules,
+        handicaps: (Handicap, Handicap),
         outcome: GameOutcome,
         wpm: f32,
+        /// your score and the opponent's score, in time attack
+        scores: Option<(Score, Score)>,
 
         waiting_to_restart: bool,
         opponent_waiting_to_restart: bool,
 
-        conn: Connection
+        /// None if the game had no opponent
+        conn: Option<Connection>
     },
     ConnectionConfig {
         input_y: u32,
         host: bool,
         ip: String,
-        port: u16
+        port: u16,
+        /// index into [`WordGame::rule_sets`], only used when hosting
+        rules: usize,
+        /// whether to join a tournament instead of playing a single match
+        tournament: bool
+    },
+    StartMenu {
+        selected: usize
+    },
+    PracticeReport {
+        duration: u64,
+        words: u64,
+        wpm: f32,
+        accuracy: f32,
+        mistakes: u32
+    },
+    SurvivalReport {
+        score: SurvivalScore,
+        wpm: f32,
+        /// the best runs, including this one if it made the table
+        best: Vec<SurvivalScore>,
+        /// where this run placed in `best`
+        place: Option<usize>
+    },
+    DailyReport {
+        result: DailyScore,
+        wpm: f32,
+        /// the best run of the day, which may be this one
+        best: DailyScore
+    },
+    HostingTournament {
+        host: TournamentHost,
+        /// index into [`WordGame::rule_sets`], can be changed until the tournament starts
+        rules: usize
+    },
+    /// agreeing on the rules of a match with the other player
+    Connecting {
+        handshake: Handshake,
+        conn: Connection
+    },
+    /// waiting for the next match of a tournament
+    TournamentLobby {
+        bracket: String,
+        conn: Connection,
+        /// set up once the host starts the next match
+        handshake: Option<Handshake>
     }
 }
 
@@ -54,41 +258,466 @@ impl Default for GameState {
     }
 }
 
+impl GameState {
+    pub fn connection_config() -> Self {
+        #[cfg(not(debug_assertions))]
+        return GameState::ConnectionConfig { input_y: 1, host: false, ip: "localhost".to_owned(), port: 5555, rules: 0, tournament: false };
+
+        #[cfg(debug_assertions)]
+        return GameState::ConnectionConfig { input_y: 1, host: false, ip: "bot".to_owned(), port: 5555, rules: 0, tournament: false };
+    }
+
+    pub fn join_tournament() -> Self {
+        GameState::ConnectionConfig { input_y: 1, host: false, ip: "localhost".to_owned(), port: TOURNAMENT_PORT, rules: 0, tournament: true }
+    }
+}
+
 //For state transitions that require moving out of the current state
 #[derive(Debug)]
 pub enum StateTransition {
     WinGame, LoseGame,
-    RestartGame
+    RestartGame,
+    FinishPractice,
+    FinishTimeAttack,
+    FinishSurvival,
+    FinishDaily,
+    /// the tournament host sent the bracket after a match
+    ShowBracket(String),
+    /// the connection was closed, going back to the menu
+    Disconnect,
+    /// both players agreed on the rules and handicaps, yours first
+    StartMatch(Box<GameRules>, (Handicap, Handicap))
 }
 
 pub struct WordGame {
     pub create_time: Instant,
     pub word_list: Vec<String>,
+    /// the name of the word list in [`word_list::dirs`], or the name of its file
+    pub word_list_name: String,
+    /// the metadata of the list and its words
+    pub word_list_info: WordList,
+    /// how hard each word of the list is, see [`WordList::difficulties`]
+    pub word_difficulties: Vec<f32>,
+    /// the words of the daily challenge, which are always from [`DEFAULT_WORD_LIST`]. loaded by the first one
+    pub daily_word_list: Vec<String>,
+    /// the file the word list was loaded from, reloaded when it changes
+    pub word_list_path: PathBuf,
+    word_list_modified: Option<SystemTime>,
+    /// the lists in [`DROP_DIR`] and when they were modified, to notice new ones
+    dropped_lists: Vec<(PathBuf, SystemTime)>,
+    last_reload_check: Instant,
     pub draw_rect: Rect,
     pub state: GameState,
+    /// command used to launch an external bot when connecting to "external"
+    pub bot_command: Option<String>,
+    /// the rules that can be picked when hosting. solo modes use the first ones that suit them
+    pub rule_sets: Vec<GameRules>,
+    /// index of your handicap in [`HANDICAPS`]
+    pub handicap: usize,
+    /// what others see you as in tournaments
+    pub player_name: String,
 
     queued_transitions: Vec<StateTransition>
 }
 
-const WORD_LIMIT: usize = 20;
+/// picks an index below `len`, more likely the higher its weight. only a few random candidates are weighed, so
+/// picking stays cheap with long lists
+pub fn pick_weighted(len: usize, rng: &mut impl Rng, weight: impl Fn(usize) -> f32) -> usize {
+    let candidates: Vec<(usize, f32)> = (0..WEIGHTED_CANDIDATES)
+        .map(|_| rng.gen_range(0..len))
+        .map(|i| (i, weight(i)))
+        .collect();
+
+    let mut choice = rng.gen::<f32>() * candidates.iter().map(|c| c.1).sum::<f32>();
+    for (i, weight) in candidates.iter() {
+        if choice < *weight {
+            return *i;
+        }
+        choice -= weight;
+    }
+
+    candidates[candidates.len() - 1].0
+}
+
+/// picks a random word to send as a combo bonus, preferring ones at least `min_len` characters long
+pub fn bonus_word(list: &[String], min_len: usize, rng: &mut impl Rng) -> String {
+    let mut word = &list[rng.gen_range(0..list.len())];
+    for _ in 0..10 {
+        if word.chars().count() >= min_len {
+            break;
+        }
+        word = &list[rng.gen_range(0..list.len())];
+    }
+
+    word.clone()
+}
+
+/// loads the word list called `name` from [`word_list::dirs`] or the file at `name`, in any format
+pub fn load_word_list(name: &str) -> std::io::Result<WordList> {
+    load_word_list_file(&WordList::find(name)?)
+}
+
+pub(crate) fn load_word_list_file(path: &Path) -> std::io::Result<WordList> {
+    let list = WordList::load(path)?;
+
+    if list.words.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Word list {} is empty", path.display())));
+    }
+
+    Ok(list)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// the lists in [`DROP_DIR`] and when they were modified
+fn dropped_lists() -> Vec<(PathBuf, SystemTime)> {
+    let Ok(entries) = std::fs::read_dir(user_data::path(DROP_DIR)) else {
+        return vec![];
+    };
+
+    entries.filter_map(|entry| {
+        let path = entry.ok()?.path();
+        Some((path.clone(), modified(&path).filter(|_| path.is_file())?))
+    }).collect()
+}
+
+/// parses a newline separated word list, capitalizing every word
+pub fn parse_word_list(text: &str) -> Vec<String> {
+    text.lines().filter_map(|s| {
+        let trimmed = s.trim();
+        if trimmed.is_empty() { return None; }
+
+        Some(capitalize(trimmed))
+    }).collect()
+}
 
 impl OngoingGame {
-    pub fn add_new_word(&mut self, list: &Vec<String>) {
-        let mut rng = rand::thread_rng();
-        let mut idx = rng.gen_range(0..list.len());
-        while self.current_words.contains(&list[idx]) {
-            idx = rng.gen_range(0..list.len());
+    fn new(mode: GameMode, rules: GameRules, handicaps: (Handicap, Handicap), conn: Option<Connection>) -> Self {
+        let seed = match mode {
+            GameMode::Daily { day } => daily::seed(day),
+            _ => rand::random()
+        };
+
+        let texts = rules.text.load().unwrap_or_else(|e| {
+            error!("Failed to load texts, using the word list: {}", e);
+            None
+        });
+
+        let adaptive = rules.adaptive.then(|| keystrokes::load().unwrap_or_else(|e| {
+            error!("Failed to load keystroke stats, picking words at random: {}", e);
+            KeyStats::default()
+        }));
+
+        OngoingGame {
+            texts,
+            mode,
+            next_wave_word: rules.survival.first_interval,
+            rules,
+            handicap: handicaps.0,
+            opponent_handicap: handicaps.1,
+            start_time: Instant::now(), 
+            total_words: 0, 
+            board: Board::default(),
+            current_text: String::new(),
+            clock: 0.0,
+            board_rng: StdRng::seed_from_u64(seed),
+            wave_rng: StdRng::seed_from_u64(seed.wrapping_add(1)),
+            play_rng: StdRng::seed_from_u64(seed.wrapping_add(2)),
+            typed_chars: 0,
+            last_keystroke: None,
+            keystrokes: KeyStats::default(),
+            adaptive,
+            correct_chars: 0,
+            score: Score::default(),
+            opponent_score: Score::default(),
+            done: false,
+            opponent_done: false,
+            ghost: GhostRun::default(),
+            conn
+        }
+    }
+
+    /// `handicaps` are yours and the opponent's
+    pub fn online(rules: GameRules, handicaps: (Handicap, Handicap), conn: Connection) -> Self {
+        Self::new(rules.mode(), rules, handicaps, Some(conn))
+    }
+
+    pub fn practice(duration: u64, rules: GameRules) -> Self {
+        Self::new(GameMode::Practice { duration }, rules, Default::default(), None)
+    }
+
+    pub fn survival(rules: GameRules) -> Self {
+        Self::new(GameMode::Survival, rules, Default::default(), None)
+    }
+
+    /// today's daily challenge. always uses the default rules, so that every run of the day is the same
+    pub fn daily() -> Self {
+        Self::new(GameMode::Daily { day: daily::today() }, GameRules::default(), Default::default(), None)
+    }
+
+    /// sends a packet to the opponent, if there is one
+    pub fn send_packet(&mut self, packet: Packet) -> std::io::Result<()> {
+        match &mut self.conn {
+            Some(conn) => conn.send_packet(packet),
+            None => Ok(())
+        }
+    }
+
+    /// seconds left in a timed game
+    pub fn time_left(&self) -> Option<f32> {
+        match self.mode {
+            GameMode::Versus | GameMode::Survival | GameMode::Daily { .. } => None,
+            GameMode::TimeAttack { duration, .. } | GameMode::Practice { duration } => {
+                Some(duration as f32 - self.time())
+            }
         }
+    }
 
-        self.current_words.push(list[idx].clone());
+    pub fn is_time_up(&self) -> bool {
+        self.time_left().is_some_and(|left| left <= 0.0)
+    }
+
+    /// the outcome of a finished time attack, from the final scores
+    fn time_attack_outcome(&self) -> GameOutcome {
+        let by = match self.mode {
+            GameMode::TimeAttack { score_by, .. } => score_by,
+            _ => ScoreBy::Words
+        };
+
+        match self.score.get(by).cmp(&self.opponent_score.get(by)) {
+            std::cmp::Ordering::Greater => GameOutcome::Win,
+            std::cmp::Ordering::Less => GameOutcome::Loss,
+            std::cmp::Ordering::Equal => GameOutcome::Draw
+        }
+    }
+
+    pub fn accuracy(&self) -> f32 {
+        if self.typed_chars == 0 {
+            1.0
+        } else {
+            self.correct_chars as f32 / self.typed_chars as f32
+        }
+    }
+
+    /// records the finished match and saves it so it can be raced as a ghost
+    fn save_ghost(&mut self, outcome: GameOutcome) {
+        self.ghost.finish(self.start_time.elapsed().as_secs_f32(), outcome);
+
+        if let Err(e) = self.ghost.save_run() {
+            error!("Failed to save ghost run: {}", e);
+        }
+    }
+
+    /// moves the game clock on to now, once a frame
+    pub fn tick(&mut self) {
+        self.clock = self.start_time.elapsed().as_secs_f32();
+    }
+
+    /// seconds into the game as of this frame
+    pub fn time(&self) -> f32 {
+        self.clock
+    }
+
+    /// puts new words on the board when they are due. the timing only depends on the game clock and the board,
+    /// never on the frame rate
+    pub fn spawn_words(&mut self, list: &Vec<String>, difficulties: &[f32]) {
+        if self.board.spawn_due(&self.rules, self.time()) {
+            self.add_new_word(list, difficulties);
+        }
+    }
+
+    /// puts a word on the board, weighted by the difficulty rules and your keystroke stats with adaptive rules.
+    /// the rng is used the same amount whatever is already on the board, so a seeded game always puts the same
+    /// words in the same order
+    pub fn add_new_word(&mut self, list: &Vec<String>, difficulties: &[f32]) {
+        let time = self.time();
+        // texts have no difficulty
+        let difficulty = match self.texts {
+            None => self.rules.difficulty,
+            Some(_) => WordDifficulty::Uniform
+        };
+        let list = self.texts.as_ref().unwrap_or(list);
+        let adaptive = self.adaptive.as_ref().and_then(|stats| Some((stats, stats.average_cost()?)));
+
+        let rng = &mut self.board_rng;
+        let idx = if adaptive.is_none() && difficulty == WordDifficulty::Uniform {
+            rng.gen_range(0..list.len())
+        } else {
+            pick_weighted(list.len(), rng, |i| {
+                let adaptive = adaptive.map_or(1.0, |(stats, average)| stats.weight(&list[i], average));
+                adaptive * difficulty.weight(difficulties.get(i).copied().unwrap_or(0.5), time)
+            })
+        };
+
+        self.board.add_word(&self.rules, list, idx, rng);
+    }
+
+    /// the survival wave reached so far
+    pub fn wave(&self) -> u32 {
+        self.rules.survival.wave(self.time())
+    }
+
+    /// sends the words of the current survival wave at the player, as if an opponent had sent them
+    pub fn send_wave_words(&mut self, list: &[String]) {
+        let time = self.time();
+        let wave = self.wave();
+
+        while self.next_wave_word <= time {
+            let word = bonus_word(list, self.rules.survival.min_length(wave), &mut self.wave_rng);
+            let weight = self.rules.attack.sent_weight(&word);
+            self.receive_word(word, weight);
+
+            self.next_wave_word += self.rules.survival.interval(wave);
+        }
+    }
+
+    /// queues a word sent by the opponent in the incoming lane
+    pub fn receive_word(&mut self, word: String, weight: u32) {
+        let time = self.time();
+        self.board.receive(&self.rules, self.handicap, word, weight, time);
+    }
+
+    /// moves incoming words whose delay is over onto the board
+    pub fn land_incoming(&mut self) {
+        let time = self.time();
+        self.board.land_incoming(time);
+    }
+
+    pub fn received_load(&self) -> usize {
+        self.board.received_load()
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.board.is_frozen(self.time())
+    }
+
+    /// applies the effect of a power-up the opponent cleared
+    pub fn apply_power_up(&mut self, power_up: PowerUp) {
+        info!("Applying power-up {:?}", power_up);
+        let time = self.time();
+        self.board.apply_power_up(&self.rules, power_up, time, &mut self.play_rng);
+    }
+
+    pub fn is_locked_out(&self) -> bool {
+        self.board.is_locked_out(self.time())
+    }
+
+    /// types a character, recording how long it took and whether it still fits a word on the board
+    pub fn type_char(&mut self, c: char) {
+        let time = self.start_time.elapsed().as_secs_f32();
+        let secs = self.last_keystroke.map(|t| time - t).filter(|s| !self.current_text.is_empty() && *s < MAX_KEY_SECS);
+        self.last_keystroke = Some(time);
+
+        let prev = self.current_text.chars().last();
+        // the letter the first word that fit before this one expected next
+        let expected = self.board.current_words.iter().chain(self.board.received_words.iter())
+            .find_map(|w| self.typed_prefix(w).and_then(|n| w.chars().nth(n)));
+
+        self.current_text.push(c);
+        self.typed_chars += 1;
+
+        let hit = self.board.current_words.iter().chain(self.board.received_words.iter()).any(|w| self.typed_prefix(w).is_some());
+        if hit {
+            self.keystrokes.keystroke(prev, c, secs, true);
+        } else if let Some(expected) = expected {
+            self.keystrokes.keystroke(prev, expected, None, false);
+        }
+    }
+
+    /// adds the keystrokes of this game to the saved stats
+    fn save_keystrokes(&mut self) {
+        let keystrokes = std::mem::take(&mut self.keystrokes);
+        if keystrokes.is_empty() {
+            return;
+        }
+
+        if let Err(e) = keystrokes::record(&keystrokes) {
+            error!("Failed to save keystroke stats: {}", e);
+        }
+    }
+
+    /// whether typing is currently ignored
+    pub fn can_type(&self) -> bool {
+        !self.done && !self.is_frozen() && !self.is_locked_out()
+    }
+
+    /// whether the strike limit has been reached under the strikes penalty
+    pub fn struck_out(&self) -> bool {
+        self.board.struck_out(&self.rules)
+    }
+
+    /// the text on the board being typed, when it has to be typed exactly.
+    /// None while typing one of the received words instead
+    pub fn typing_target(&self) -> Option<&str> {
+        if !self.rules.text.in_order() {
+            return None;
+        }
+
+        if self.board.received_words.iter().any(|w| self.typed_prefix(w).is_some()) {
+            return None;
+        }
+
+        self.board.current_words.first().map(|w| w.trim_start())
+    }
+
+    /// how many characters of `word` the current text has typed, if it is the start of it
+    pub fn typed_prefix(&self, word: &str) -> Option<usize> {
+        typed_prefix(word, &self.current_text, self.rules.ignore_accents)
+    }
+
+    /// the current streak of clears, or 0 once the combo window has passed
+    pub fn combo(&self) -> u32 {
+        self.board.combo(&self.rules, self.time())
+    }
+
+    /// clears every word matching the current text, sending the ones from your own board to the opponent.
+    /// streaks of clears send bonus words from `list` as well
+    pub fn submit(&mut self, list: &[String]) -> std::io::Result<()> {
+        if !self.can_type() {
+            return Ok(());
+        }
+
+        let time = self.time();
+        let text = std::mem::take(&mut self.current_text);
+        let submission = self.board.submit(&self.rules, &text, false, list, time, &mut self.play_rng);
+
+        for word in submission.cleared.iter() {
+            self.ghost.record(time, GhostEventKind::Cleared, word);
+            self.score.add_word(word);
+            // quotes count as all of their words
+            self.total_words += word.split_whitespace().count() as u64;
+        }
+        if !submission.cleared.is_empty() {
+            self.correct_chars += text.chars().count() as u64;
+        }
+
+        if let Some(conn) = &mut self.conn {
+            for (word, weight) in submission.sent.iter() {
+                debug!("Sending '{}' weighing {}", word, weight);
+                self.ghost.record(time, GhostEventKind::Sent, word);
+                conn.send_packet(Packet::attack(word, *weight))?;
+            }
+
+            if let (GameMode::TimeAttack { .. }, false) = (self.mode, submission.cleared.is_empty()) {
+                conn.send_packet(Packet::Score { score: self.score, done: false })?;
+            }
+        }
+
+        for power_up in submission.power_ups {
+            self.send_packet(Packet::PowerUp { power_up })?;
+        }
+
+        Ok(())
     }
 
     pub fn wpm(&self) -> f32 {
-        self.total_words as f32 / self.start_time.elapsed().as_secs_f32()
+        self.total_words as f32 * 60.0 / self.start_time.elapsed().as_secs_f32()
     }
 
     pub fn limit(&self) -> usize {
-        (WORD_LIMIT*2 - (self.start_time.elapsed().as_secs() as usize / (120 / WORD_LIMIT))).min(WORD_LIMIT)
+        self.rules.limit.limit_at(self.time()) + self.handicap.extra_limit
     }
 }
 
@@ -96,20 +725,6 @@ impl WordGame {
     pub fn new(ctx: &mut Context, word_list: &str) -> WordGame {
         ctx.fs.mount(Path::new("./res"), true);
 
-        let words = std::io::read_to_string(ctx.fs.open(format!("/words/{word_list}.txt")).unwrap()).unwrap();
-        let words: Lines = words.lines();
-        let words = words.filter_map(|s| {
-            let trimmed = s.trim();
-            if trimmed.len() == 0 { return None; }
-
-            let first_char: String = trimmed.chars().nth(0).unwrap().to_uppercase().collect();
-            let rest: String = trimmed.chars().skip(1).flat_map(|c| c.to_lowercase()).collect();
-
-            Some(format!("{}{}", first_char, rest))
-        }).collect();
-
-
-
         ctx.gfx.add_font(
             "courier_new", 
             match FontData::from_path(ctx, "C:/Windows/Fonts/cour.ttf") {
@@ -118,17 +733,128 @@ impl WordGame {
             }
         );
 
-        WordGame {
+        let mut game = WordGame {
             create_time: Instant::now(),
-            word_list: words,
+            word_list: vec![],
+            word_list_name: String::new(),
+            word_list_info: WordList::default(),
+            word_difficulties: vec![],
+            daily_word_list: vec![],
+            word_list_path: PathBuf::new(),
+            word_list_modified: None,
+            dropped_lists: dropped_lists(),
+            last_reload_check: Instant::now(),
             draw_rect: Rect::one(),
-            #[cfg(not(debug_assertions))]
-            state: GameState::ConnectionConfig { input_y: 1, host: false, ip: "localhost".to_owned(), port: 5555 },
-            
-            #[cfg(debug_assertions)]
-            state: GameState::ConnectionConfig { input_y: 1, host: false, ip: "bot".to_owned(), port: 5555 },
+            state: GameState::StartMenu { selected: 0 },
+
+            bot_command: None,
+            rule_sets: GameRules::load_all(),
+            handicap: 0,
+            player_name: std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "player".to_owned()),
 
             queued_transitions: vec![]
+        };
+
+        game.set_word_list(word_list).unwrap();
+        if let Err(e) = std::fs::create_dir_all(user_data::path(DROP_DIR)) {
+            warn!("Couldn't create the folder for dropped word lists: {}", e);
+        }
+        game
+    }
+
+    /// switches to the list called `name`, or the list file at `name`
+    pub fn set_word_list(&mut self, name: &str) -> std::io::Result<()> {
+        let path = WordList::find(name)?;
+        let list = load_word_list_file(&path)?;
+        self.use_word_list(word_list::display_name(name), list, path);
+
+        Ok(())
+    }
+
+    /// plays with `list`, reloading it from `path` when that changes. an empty path is never reloaded
+    fn use_word_list(&mut self, name: String, list: WordList, path: PathBuf) {
+        info!(
+            "Playing with word list {} ({} words){}",
+            list.name.as_deref().unwrap_or(&name),
+            list.words.len(),
+            list.license.as_ref().map_or(String::new(), |l| format!(", licensed {l}"))
+        );
+
+        self.word_list = list.words();
+        self.word_difficulties = list.difficulties();
+        self.word_list_name = name;
+        self.word_list_info = list;
+        self.word_list_modified = modified(&path);
+        self.word_list_path = path;
+    }
+
+    /// reloads the word list if its file changed since it was loaded. only looks at the file every
+    /// [`WORD_LIST_RELOAD_CHECK`], and keeps the old words if the new ones can't be loaded
+    pub fn reload_changed_word_list(&mut self) {
+        if self.last_reload_check.elapsed() < WORD_LIST_RELOAD_CHECK {
+            return;
+        }
+        self.last_reload_check = Instant::now();
+
+        // copies can keep the time the original was modified, so any list that wasn't there before counts
+        let dropped = dropped_lists();
+        let new = dropped.iter().filter(|list| !self.dropped_lists.contains(list)).max_by_key(|(_, modified)| *modified).cloned();
+        self.dropped_lists = dropped;
+        if let Some((path, _)) = new {
+            return self.drop_file(&path);
+        }
+
+        let modified = modified(&self.word_list_path);
+        if modified == self.word_list_modified {
+            return;
+        }
+        self.word_list_modified = modified;
+
+        match load_word_list_file(&self.word_list_path) {
+            Ok(list) => {
+                info!("Reloaded word list {} ({} words)", self.word_list_name, list.words.len());
+                self.word_list = list.words();
+                self.word_difficulties = list.difficulties();
+                self.word_list_info = list;
+            },
+            Err(e) => warn!("Keeping the old words, couldn't reload {}: {}", self.word_list_path.display(), e)
+        }
+    }
+
+    /// today's daily challenge. it's played with [`DEFAULT_WORD_LIST`] whatever list is selected, so everyone
+    /// gets the same words
+    pub fn daily_game(&mut self) -> Option<OngoingGame> {
+        if self.daily_word_list.is_empty() {
+            match load_word_list(DEFAULT_WORD_LIST) {
+                Ok(list) => self.daily_word_list = list.words(),
+                Err(e) => {
+                    error!("Failed to load the daily challenge's word list: {}", e);
+                    return None;
+                }
+            }
+        }
+
+        Some(OngoingGame::daily())
+    }
+
+    /// plays with a list copied into [`DROP_DIR`]
+    fn drop_file(&mut self, path: &Path) {
+        if let Err(e) = self.set_word_list(&path.to_string_lossy()) {
+            error!("Couldn't load dropped word list {}: {}", path.display(), e);
+        }
+    }
+
+    /// switches to the word list after the current one in [`word_list::dirs`]
+    pub fn next_word_list(&mut self) {
+        let names = word_list::names();
+        let current = names.iter().position(|n| *n == self.word_list_name);
+
+        // skip over lists that fail to load
+        for name in names.iter().cycle().skip(current.map_or(0, |i| i + 1)).take(names.len()) {
+            match self.set_word_list(name) {
+                Ok(()) => return,
+                Err(e) => error!("Skipping word list {}: {}", name, e)
+            }
         }
     }
 
@@ -140,38 +866,110 @@ impl WordGame {
         let Self{state, queued_transitions, ..} = self;
 
         for transition in queued_transitions.iter() {
+            // every transition out of a game ends it
+            if let GameState::Ongoing(ongoing) = state {
+                ongoing.save_keystrokes();
+            }
+
             let prev_state = std::mem::take(state);
             *state = match (transition, prev_state) {
-                (StateTransition::WinGame, GameState::Ongoing(ongoing)) => {
+                (StateTransition::WinGame, GameState::Ongoing(mut ongoing)) => {
+                    ongoing.save_ghost(GameOutcome::Win);
                     GameState::Ended {
-                        outcome: GameOutcome::Win, 
                         wpm: ongoing.wpm(), 
+                        rules: ongoing.rules,
+                        handicaps: (ongoing.handicap, ongoing.opponent_handicap),
+                        outcome: GameOutcome::Win, 
+                        scores: None,
                         waiting_to_restart: false, 
                         opponent_waiting_to_restart: false, 
                         conn: ongoing.conn
                     }
                 },
-                (StateTransition::LoseGame, GameState::Ongoing(ongoing)) => {
+                (StateTransition::LoseGame, GameState::Ongoing(mut ongoing)) => {
+                    ongoing.save_ghost(GameOutcome::Loss);
                     GameState::Ended {
-                        outcome: GameOutcome::Loss, 
                         wpm: ongoing.wpm(), 
+                        rules: ongoing.rules,
+                        handicaps: (ongoing.handicap, ongoing.opponent_handicap),
+                        outcome: GameOutcome::Loss, 
+                        scores: None,
                         waiting_to_restart: false, 
                         opponent_waiting_to_restart: false, 
                         conn: ongoing.conn
                     }
                 },
-                (StateTransition::RestartGame, GameState::Ended { conn, .. }) => {
-                    GameState::Ongoing(OngoingGame {
-                        start_time: Instant::now(), 
-                        total_words: 0, 
-                        current_words: vec![], 
-                        received_words: vec![], 
-                        last_new_word: Instant::now(), 
-                        current_text: String::new(), 
-                        conn
-                    })
+                (StateTransition::RestartGame, GameState::Ended { rules, handicaps, conn, .. }) => {
+                    GameState::Ongoing(OngoingGame::new(rules.mode(), rules, handicaps, conn))
+                },
+                (StateTransition::FinishTimeAttack, GameState::Ongoing(mut ongoing)) => {
+                    let outcome = ongoing.time_attack_outcome();
+                    ongoing.save_ghost(outcome);
+                    GameState::Ended {
+                        wpm: ongoing.wpm(),
+                        rules: ongoing.rules,
+                        handicaps: (ongoing.handicap, ongoing.opponent_handicap),
+                        outcome,
+                        scores: Some((ongoing.score, ongoing.opponent_score)),
+                        waiting_to_restart: false,
+                        opponent_waiting_to_restart: false,
+                        conn: ongoing.conn
+                    }
+                },
+                (StateTransition::FinishPractice, GameState::Ongoing(ongoing)) => {
+                    GameState::PracticeReport {
+                        duration: match ongoing.mode {
+                            GameMode::Practice { duration } => duration,
+                            _ => ongoing.start_time.elapsed().as_secs()
+                        },
+                        words: ongoing.total_words,
+                        wpm: ongoing.wpm(),
+                        accuracy: ongoing.accuracy(),
+                        mistakes: ongoing.board.mistakes
+                    }
+                },
+                (StateTransition::FinishSurvival, GameState::Ongoing(ongoing)) => {
+                    let score = SurvivalScore {
+                        time: ongoing.start_time.elapsed().as_secs_f32(),
+                        wave: ongoing.wave(),
+                        words: ongoing.total_words
+                    };
+                    let (best, place) = survival::record(score).unwrap_or_else(|e| {
+                        error!("Failed to save survival score: {}", e);
+                        (vec![], None)
+                    });
+
+                    GameState::SurvivalReport { score, wpm: ongoing.wpm(), best, place }
+                },
+                (StateTransition::FinishDaily, GameState::Ongoing(ongoing @ OngoingGame { mode: GameMode::Daily { day }, .. })) => {
+                    let result = DailyScore {
+                        day,
+                        score: SurvivalScore {
+                            time: ongoing.start_time.elapsed().as_secs_f32(),
+                            wave: ongoing.wave(),
+                            words: ongoing.total_words
+                        }
+                    };
+                    info!("Daily challenge score: {}", result.export());
+
+                    let best = daily::record(result).unwrap_or_else(|e| {
+                        error!("Failed to save daily score: {}", e);
+                        result
+                    });
+
+                    GameState::DailyReport { result, wpm: ongoing.wpm(), best }
+                },
+                (StateTransition::Disconnect, _) => GameState::StartMenu { selected: 0 },
+                (StateTransition::ShowBracket(bracket), GameState::Ended { conn: Some(conn), .. }) => {
+                    GameState::TournamentLobby { bracket: bracket.clone(), conn, handshake: None }
+                },
+                (StateTransition::StartMatch(rules, handicaps), GameState::Connecting { conn, .. } | GameState::TournamentLobby { conn, .. }) => {
+                    GameState::Ongoing(OngoingGame::online((**rules).clone(), *handicaps, conn))
+                },
+                (t, s) => {
+                    error!("Ignoring invalid transition {:?} for state {:?}", t, s);
+                    s
                 }
-                (t, s) => panic!("Invalid transition {:?} for state {:?}", t, s)
             };
         }
 
@@ -180,20 +978,36 @@ impl WordGame {
 
     /// detects if word has been sent, and if so adds it to list of received words
     pub fn process_network(&mut self) -> GameResult {
+        let mut agreed = Ok(None);
+
         match self.state {
-            GameState::Ongoing(ref mut ongoing) => {
+            GameState::Ongoing(ref mut ongoing @ OngoingGame { conn: Some(_), .. }) => {
                 loop {
-                    let packet = ongoing.conn.poll_next_packet()?;
+                    let packet = match ongoing.conn.as_mut().unwrap().poll_next_packet() {
+                        Ok(packet) => packet,
+                        Err(e) => {
+                            error!("Lost the connection to the opponent: {}", e);
+                            self.queue_transition(StateTransition::Disconnect);
+                            break;
+                        }
+                    };
 
                     match packet {
                         None => break,
-                        Some(Packet::AddWord { word }) => {
-                            ongoing.received_words.push(word);
+                        Some(Packet::AddWord { word, weight }) => {
+                            ongoing.receive_word(word, weight);
                         },
                         Some(Packet::ILost {  }) => {
                             self.queue_transition(StateTransition::WinGame);
                             break;
                         },
+                        Some(Packet::Score { score, done }) => {
+                            ongoing.opponent_score = score;
+                            ongoing.opponent_done = done;
+                        },
+                        Some(Packet::PowerUp { power_up }) => {
+                            ongoing.apply_power_up(power_up);
+                        },
 
                         Some(p) => {
                             warn!("Unexpected packed {:?} received in ongoing state!", p)
@@ -201,14 +1015,25 @@ impl WordGame {
                     }
                 }
             },
-            GameState::Ended { ref mut opponent_waiting_to_restart, ref mut conn, .. } => {
+            GameState::Ended { ref mut opponent_waiting_to_restart, conn: Some(ref mut conn), .. } => {
                 loop {
-                    let packet = conn.poll_next_packet()?;
+                    let packet = match conn.poll_next_packet() {
+                        Ok(packet) => packet,
+                        Err(e) => {
+                            error!("Lost the connection to the opponent: {}", e);
+                            self.queue_transition(StateTransition::Disconnect);
+                            break;
+                        }
+                    };
 
                     match packet {
                         None => break,
                         Some(Packet::WaitingToRestart) => {
                             *opponent_waiting_to_restart = true;
+                        },
+                        Some(Packet::Bracket { text }) => {
+                            self.queue_transition(StateTransition::ShowBracket(text));
+                            break;
                         }
 
                         Some(p) => {
@@ -217,18 +1042,166 @@ impl WordGame {
                     }
                 }
             },
+            GameState::TournamentLobby { ref mut bracket, ref mut conn, ref mut handshake } => {
+                loop {
+                    let packet = match conn.poll_next_packet() {
+                        Ok(packet) => packet,
+                        Err(e) => {
+                            error!("Lost the connection to the tournament host: {}", e);
+                            self.queue_transition(StateTransition::Disconnect);
+                            break;
+                        }
+                    };
+
+                    match packet {
+                        None => {
+                            if let Some(handshake) = handshake {
+                                agreed = handshake.check_timeout().map(|_| None);
+                            }
+                            break;
+                        },
+                        Some(Packet::Bracket { text }) => {
+                            *bracket = text;
+                        },
+                        // the host answers the registration with its version
+                        Some(Packet::ClientInfo { major, minor }) => if let Err(e) = check_version(major, minor) {
+                            error!("Can't join the tournament: {}", e);
+                            self.queue_transition(StateTransition::Disconnect);
+                            break;
+                        },
+                        Some(packet) => {
+                            // the host starts the next match with its settings
+                            if let Packet::MatchSettings { .. } = packet {
+                                handshake.get_or_insert_with(|| Handshake::tournament_match(HANDICAPS[self.handicap]));
+                            }
+
+                            let Some(handshake) = handshake else {
+                                warn!("Unexpected packet {:?} received in tournament lobby!", packet);
+                                continue;
+                            };
+                            agreed = handshake.handle(conn, packet, (&self.word_list_name, &self.word_list_info));
+                            if !matches!(agreed, Ok(None)) {
+                                break;
+                            }
+                        }
+                    }
+                }
+            },
+            GameState::Connecting { ref mut handshake, ref mut conn } => {
+                agreed = handshake.poll(conn, (&self.word_list_name, &self.word_list_info));
+            },
+            GameState::HostingTournament { ref mut host, .. } => {
+                host.poll()?;
+            },
             _ => {}
         }
 
+        match agreed {
+            Ok(Some(agreement)) => {
+                let (yours, theirs) = agreement.handicaps;
+                info!("Connected! Playing {} rules, handicaps {} v {}", agreement.rules.name, yours.name, theirs.name);
+                if let Some((name, list, path)) = agreement.word_list {
+                    self.use_word_list(name, list, path);
+                }
+                self.queue_transition(StateTransition::StartMatch(Box::new(agreement.rules), agreement.handicaps));
+            },
+            Ok(None) => {},
+            Err(e) => {
+                error!("Failed to set up match: {}", e);
+                self.queue_transition(StateTransition::Disconnect);
+            }
+        }
         self.flush_transitions();
 
         Ok(())
     }
 
+    /// index into [`WordGame::rule_sets`] of the first rules that suit `mode`. the default rules suit every solo mode
+    pub fn rules_for(&self, mode: GameMode) -> usize {
+        self.rule_sets.iter().position(|r| r.suits(mode)).unwrap_or(0)
+    }
+
+    pub fn practice_game(&self, duration: u64) -> OngoingGame {
+        OngoingGame::practice(duration, self.rule_sets[self.rules_for(GameMode::Practice { duration })].clone())
+    }
+
+    pub fn survival_game(&self) -> OngoingGame {
+        OngoingGame::survival(self.rule_sets[self.rules_for(GameMode::Survival)].clone())
+    }
+
+    pub fn select_menu_option(&mut self, option: MenuOption) {
+        self.state = match option {
+            MenuOption::Versus => GameState::connection_config(),
+            MenuOption::Practice(duration) => GameState::Ongoing(self.practice_game(duration)),
+            MenuOption::Survival => GameState::Ongoing(self.survival_game()),
+            MenuOption::Daily => match self.daily_game() {
+                Some(daily) => GameState::Ongoing(daily),
+                None => return
+            },
+            // the host can switch to other rules before it starts
+            MenuOption::HostTournament => match TournamentHost::bind(
+                TOURNAMENT_PORT,
+                self.rule_sets[self.rules_for(GameMode::Versus)].clone(),
+                (self.word_list_name.clone(), self.word_list_info.clone())
+            ) {
+                Ok(host) => GameState::HostingTournament { host, rules: self.rules_for(GameMode::Versus) },
+                Err(e) => {
+                    error!("Failed to host tournament: {}", e);
+                    return;
+                }
+            },
+            MenuOption::JoinTournament => GameState::join_tournament(),
+            MenuOption::WordList => {
+                self.next_word_list();
+                return;
+            }
+        };
+    }
+
+    /// the host picks the rules of the match and tells the other player, then both players exchange handicaps
+    /// and the other player switches to the host's word list.
+    /// returns the rules and the handicaps of both players, yours first
+    /// registers with the tournament host, then waits in the lobby for the first match
+    fn join_tournament_ui(&mut self) {
+        let GameState::ConnectionConfig { ip, port, .. } = &self.state else {
+            return;
+        };
+
+        info!("Joining tournament at {}:{}", ip, port);
+        let joined = TcpStream::connect((ip.as_str(), *port))
+            .and_then(Connection::new)
+            .and_then(|mut conn| {
+                conn.send_packet(Packet::client_info())?;
+                conn.send_packet(Packet::Register { name: self.player_name.clone(), handicap: HANDICAPS[self.handicap] })?;
+                Ok(conn)
+            });
+
+        match joined {
+            Ok(conn) => self.state = GameState::TournamentLobby { bracket: "waiting for the host...".to_owned(), conn, handshake: None },
+            Err(e) => error!("Failed to join tournament: {}", e)
+        }
+    }
+
     pub fn pair_up_ui(&mut self) {
+        if let GameState::ConnectionConfig { tournament: true, .. } = self.state {
+            return self.join_tournament_ui();
+        }
+
         let conn = match &self.state {
             GameState::ConnectionConfig { host: true, ip, .. } if ip == "bot" => {
-                connect_to_dummy().ok()
+                connect_to_dummy(self.word_list.clone()).ok()
+            },
+            GameState::ConnectionConfig { host: true, ip, .. } if ip == "ghost" => {
+                connect_to_ghost().ok()
+            },
+            GameState::ConnectionConfig { host: true, ip, .. } if ip == "external" => {
+                match &self.bot_command {
+                    Some(command) => connect_to_external_bot(command, self.word_list.clone()).map_err(|e| error!("Failed to start external bot: {}", e)).ok(),
+                    None => {
+                        error!("No external bot configured, launch the game with --bot-command <command>");
+                        None
+                    }
+                }
             },
             GameState::ConnectionConfig { host: true, ip, port, .. } => {
                 if let Ok(listener) = TcpListener::bind((ip.as_str(), *port)) {
@@ -256,19 +1229,22 @@ impl WordGame {
             }
         };
             
-        if let Some(conn) = conn {
-            info!("Connected!");
-            self.state = GameState::Ongoing(OngoingGame {
-                start_time: Instant::now(), 
-                total_words: 0, 
-                current_words: vec![], 
-                received_words: vec![], 
-                last_new_word: Instant::now(), 
-                current_text: String::new(), 
-                conn
-            });
-        } else {
-            error!("Failed to connect!");
+        let Some(mut conn) = conn else {
+            return error!("Failed to connect!");
+        };
+
+        let handicap = HANDICAPS[self.handicap];
+        let handshake = match self.state {
+            GameState::ConnectionConfig { host: true, rules, .. } => {
+                let rules = self.rule_sets[rules].clone();
+                Handshake::host(&mut conn, rules, handicap, (&self.word_list_name, &self.word_list_info))
+            },
+            _ => Handshake::join(&mut conn, handicap)
+        };
+
+        match handshake {
+            Ok(handshake) => self.state = GameState::Connecting { handshake, conn },
+            Err(e) => error!("Failed to set up match: {}", e)
         }
     }
 }
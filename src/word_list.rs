@@ -0,0 +1,563 @@
+//! Word lists in `res/words`. Plain `.txt` lists have one word per line, but lists can also describe themselves
+//! and their words, as `.toml`:
+//! ```toml
+//! name = "French"
+//! description = "Common french words"
+//! license = "CC-BY-SA 4.0"
+//! language = "fr"
+//!
+//! [[words]]
+//! word = "bonjour"
+//! frequency = 120.0   # occurrences per million words
+//! difficulty = 0.2    # from 0 for easy to 1 for hard
+//! tags = ["greeting"]
+//! language = "fr"     # if it isn't the list's language
+//! ```
+//! or as `.csv`, with `# key: value` lines for the list and a header row naming the columns. Only `word` is required,
+//! tags are separated by `;` and fields with commas in them are double-quoted, doubling any quotes they contain:
+//! ```text
+//! # name: French
+//! # license: CC-BY-SA 4.0
+//! word,frequency,difficulty,tags
+//! bonjour,120,0.2,greeting;polite
+//! "c'est-à-dire, enfin",3,0.7,
+//! ```
+//!
+//! Lists are looked up by name in `res/words`, then in the `words` folder of the [`crate::user_data`] folder. A path to
+//! any list file can be used instead of a name, and a list copied into the `drop` folder is played with right away.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::text::{capitalize, fold};
+use crate::user_data;
+
+pub const WORDS_DIR: &str = "res/words";
+/// the folder in the user's data folder that lists are also looked up in
+const USER_WORDS_DIR: &str = "words";
+/// words this short are rated as easy as can be, and this long as hard
+const MIN_LENGTH: f32 = 3.0;
+const MAX_LENGTH: f32 = 12.0;
+/// the formats a list can be in, the first existing one is used
+pub const EXTENSIONS: [&str; 3] = ["toml", "csv", "txt"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WordEntry {
+    pub word: String,
+    pub frequency: Option<f32>,
+    pub difficulty: Option<f32>,
+    pub tags: Vec<String>,
+    pub language: Option<String>
+}
+
+impl WordEntry {
+    pub fn new(word: &str) -> Self {
+        WordEntry { word: word.to_owned(), ..Default::default() }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WordList {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub language: Option<String>,
+    pub words: Vec<WordEntry>
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().map_or(String::new(), |e| e.to_string_lossy().into_owned())
+}
+
+/// quotes `field` if it has to be to fit in a csv row
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// splits a csv row into its trimmed fields, unquoting the quoted ones
+fn csv_fields(line: &str) -> io::Result<Vec<String>> {
+    let mut fields = vec![];
+    let mut chars = line.chars().peekable();
+
+    loop {
+        let mut field = String::new();
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') => match chars.next_if_eq(&'"') {
+                        Some(_) => field.push('"'),
+                        None => break
+                    },
+                    Some(c) => field.push(c),
+                    None => return Err(invalid(format!("Unclosed quote in '{line}'")))
+                }
+            }
+
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_some_and(|c| *c != ',') {
+                return Err(invalid(format!("Text after a quoted field in '{line}'")));
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                field.push(c);
+            }
+            field.truncate(field.trim_end().len());
+        }
+
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+impl WordList {
+    /// one word per line
+    pub fn parse_txt(text: &str) -> Self {
+        WordList {
+            words: text.lines().map(str::trim).filter(|l| !l.is_empty()).map(WordEntry::new).collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn parse_toml(text: &str) -> io::Result<Self> {
+        toml::from_str(text).map_err(|e| invalid(e.to_string()))
+    }
+
+    pub fn parse_csv(text: &str) -> io::Result<Self> {
+        let mut list = WordList::default();
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+
+        let header = loop {
+            let line = lines.next().ok_or_else(|| invalid("Missing csv header".to_owned()))?;
+
+            match line.strip_prefix('#') {
+                Some(meta) => {
+                    let (key, value) = meta.split_once(':').ok_or_else(|| invalid(format!("Invalid metadata '{line}'")))?;
+                    let value = Some(value.trim().to_owned());
+
+                    match key.trim() {
+                        "name" => list.name = value,
+                        "description" => list.description = value,
+                        "license" => list.license = value,
+                        "language" => list.language = value,
+                        key => warn!("Ignoring unknown word list metadata {}", key)
+                    }
+                },
+                None => break line
+            }
+        };
+
+        let columns = csv_fields(header)?;
+        if !columns.iter().any(|c| c == "word") {
+            return Err(invalid("The csv header has no word column".to_owned()));
+        }
+
+        for line in lines {
+            let mut entry = WordEntry::default();
+
+            let fields = csv_fields(line)?;
+            if fields.len() > columns.len() {
+                return Err(invalid(format!("More fields than columns in '{line}'")));
+            }
+
+            for (column, field) in columns.iter().zip(fields.iter()) {
+                let number = || field.parse::<f32>().map_err(|_| invalid(format!("Invalid {column} '{field}' in '{line}'")));
+
+                match column.as_str() {
+                    _ if field.is_empty() => {},
+                    "word" => entry.word = field.clone(),
+                    "frequency" => entry.frequency = Some(number()?),
+                    "difficulty" => entry.difficulty = Some(number()?),
+                    "tags" => entry.tags = field.split(';').map(|t| t.trim().to_owned()).filter(|t| !t.is_empty()).collect(),
+                    "language" => entry.language = Some(field.clone()),
+                    _ => {}
+                }
+            }
+
+            if entry.word.is_empty() {
+                return Err(invalid(format!("Missing word in '{line}'")));
+            }
+            list.words.push(entry);
+        }
+
+        Ok(list)
+    }
+
+    /// parses a list according to the extension of its file, keeping the words as they are written
+    pub fn parse(text: &str, extension: &str) -> io::Result<Self> {
+        let mut list = match extension {
+            "toml" => Self::parse_toml(text)?,
+            "csv" => Self::parse_csv(text)?,
+            _ => Self::parse_txt(text)
+        };
+
+        list.words.retain(|e| !e.word.trim().is_empty());
+        for entry in list.words.iter_mut() {
+            entry.word = entry.word.trim().to_owned();
+        }
+
+        Ok(list)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        Self::parse(&fs::read_to_string(path)?, &extension(path))
+    }
+
+    /// reads a list to play with, capitalizing every word
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut list = Self::read(path)?;
+        for entry in list.words.iter_mut() {
+            entry.word = capitalize(&entry.word);
+        }
+
+        Ok(list)
+    }
+
+    pub fn to_toml(&self) -> io::Result<String> {
+        toml::to_string(self).map_err(|e| invalid(e.to_string()))
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for (key, value) in [("name", &self.name), ("description", &self.description), ("license", &self.license), ("language", &self.language)] {
+            if let Some(value) = value {
+                csv += &format!("# {key}: {value}\n");
+            }
+        }
+
+        csv += "word,frequency,difficulty,tags,language\n";
+        for entry in self.words.iter() {
+            csv += &format!(
+                "{},{},{},{},{}\n",
+                csv_field(&entry.word),
+                entry.frequency.map_or(String::new(), |f| f.to_string()),
+                entry.difficulty.map_or(String::new(), |d| d.to_string()),
+                csv_field(&entry.tags.join(";")),
+                csv_field(entry.language.as_deref().unwrap_or(""))
+            );
+        }
+
+        csv
+    }
+
+    /// identifies the list and its metadata, so players can tell whether they have the same list
+    pub fn hash(&self) -> u64 {
+        // fnv-1a
+        self.to_csv().bytes().fold(0xcbf29ce484222325, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+    }
+
+    /// saves the list in the format matching the extension of `path`
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let text = match extension(path).as_str() {
+            "toml" => self.to_toml()?,
+            "csv" => self.to_csv(),
+            _ => self.words.iter().map(|e| format!("{}\n", e.word)).collect()
+        };
+
+        fs::write(path, text)
+    }
+
+    /// the path of the list called `name` in [`dirs`], in whichever format it is. `name` can also be a path to a list
+    pub fn find(name: &str) -> io::Result<PathBuf> {
+        if Path::new(name).is_file() {
+            return Ok(PathBuf::from(name));
+        }
+
+        dirs().iter()
+            .flat_map(|dir| EXTENSIONS.iter().map(move |e| dir.join(format!("{name}.{e}"))))
+            .find(|p| p.exists())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No word list called {name}")))
+    }
+
+    /// the words, without their metadata
+    pub fn words(&self) -> Vec<String> {
+        self.words.iter().map(|e| e.word.clone()).collect()
+    }
+
+    /// how hard each word is, from 0 to 1. words without a difficulty are rated by their length and how rare they
+    /// are, going by their frequency, or by their place in the list if it has none as lists are usually sorted
+    /// from most to least common
+    pub fn difficulties(&self) -> Vec<f32> {
+        let mut by_frequency: Vec<usize> = (0..self.words.len()).collect();
+        if self.words.iter().all(|e| e.frequency.is_some()) {
+            by_frequency.sort_by(|a, b| self.words[*b].frequency.unwrap_or(0.0).total_cmp(&self.words[*a].frequency.unwrap_or(0.0)));
+        }
+
+        let mut rarity = vec![0.0; self.words.len()];
+        for (rank, i) in by_frequency.into_iter().enumerate() {
+            rarity[i] = rank as f32 / self.words.len().saturating_sub(1).max(1) as f32;
+        }
+
+        self.words.iter().zip(rarity).map(|(entry, rarity)| entry.difficulty.unwrap_or_else(|| {
+            let length = (entry.word.chars().count() as f32 - MIN_LENGTH) / (MAX_LENGTH - MIN_LENGTH);
+            (length.clamp(0.0, 1.0) + rarity) / 2.0
+        })).collect()
+    }
+}
+
+/// the folders lists are looked up in, in order: [`WORDS_DIR`] then the user's data folder
+pub fn dirs() -> Vec<PathBuf> {
+    [Some(PathBuf::from(WORDS_DIR)), user_data::dir().map(|d| d.join(USER_WORDS_DIR))].into_iter().flatten().collect()
+}
+
+/// the name to show for the list `name`, which is the file name without its extension if it's a path
+pub fn display_name(name: &str) -> String {
+    match Path::new(name).file_stem() {
+        Some(stem) if Path::new(name).is_file() => stem.to_string_lossy().into_owned(),
+        _ => name.to_owned()
+    }
+}
+
+/// the names of every word list in [`dirs`], sorted
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for dir in dirs() {
+        match fs::read_dir(&dir) {
+            Ok(entries) => names.extend(entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|e| EXTENSIONS.iter().any(|x| e == *x)))
+                .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => error!("Failed to list word lists in {}: {}", dir.display(), e)
+        }
+    }
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+const USAGE: &str = "usage: word-game words <NAME or FILE> [--top N] [--min-len N] [--max-len N] [--chars CHARS] [--regex RE] [--blocklist FILE] [--out FILE]";
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{msg}\n{USAGE}"))
+}
+
+/// `word-game words`, builds a list out of another one. filters are applied in this order:
+/// - `--top N` keeps the N most frequent words, or the first N if the list has no frequencies
+/// - `--min-len N`, `--max-len N` keep words with that many characters
+/// - `--chars CHARS` keeps words made only of those characters, ignoring case
+/// - `--regex RE` keeps words matching the regex
+/// - `--blocklist FILE` removes the words in a newline separated file, ignoring case and accents
+///
+/// then duplicates are removed, and the list is written to `--out` (in the format of its extension) or stdout.
+/// statistics about the new list are written to stderr
+///
+/// `res/words/5000_out.txt` is `word-game words lots_of --top 5000 --min-len 5 --out res/words/5000_out.txt`
+pub fn run_cli(args: &[String]) -> io::Result<()> {
+    let mut args = args.iter();
+    let input = args.next().ok_or_else(|| invalid_input("Missing word list".to_owned()))?;
+
+    let mut top = None;
+    let mut min_len = 0;
+    let mut max_len = usize::MAX;
+    let mut chars = None;
+    let mut regex = None;
+    let mut blocklist = None;
+    let mut out = None;
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| invalid_input(format!("Missing value for {arg}")))?;
+        let number = || value.parse::<usize>().map_err(|_| invalid_input(format!("Invalid number {value} for {arg}")));
+
+        match arg.as_str() {
+            "--top" => top = Some(number()?),
+            "--min-len" => min_len = number()?,
+            "--max-len" => max_len = number()?,
+            "--chars" => chars = Some(value.to_lowercase()),
+            "--regex" => regex = Some(regex::Regex::new(value).map_err(|e| invalid_input(format!("Invalid regex: {e}")))?),
+            "--blocklist" => {
+                let blocked = fs::read_to_string(value)?;
+                blocklist = Some(blocked.lines().map(|w| fold(w.trim(), true)).collect::<HashSet<_>>());
+            },
+            "--out" => out = Some(value.clone()),
+            _ => return Err(invalid_input(format!("Unknown argument {arg}")))
+        }
+    }
+
+    let path = WordList::find(input)?;
+    let mut list = WordList::read(&path)?;
+    let mut removed = vec![];
+
+    let mut filter = |list: &mut WordList, name: &str, keep: &mut dyn FnMut(&WordEntry) -> bool| {
+        let before = list.words.len();
+        list.words.retain(|e| keep(e));
+        removed.push((name.to_owned(), before - list.words.len()));
+    };
+
+    if let Some(top) = top {
+        if list.words.iter().all(|e| e.frequency.is_some()) {
+            list.words.sort_by(|a, b| b.frequency.unwrap_or(0.0).total_cmp(&a.frequency.unwrap_or(0.0)));
+        }
+
+        let mut rank = 0;
+        filter(&mut list, "rank", &mut |_| {
+            rank += 1;
+            rank <= top
+        });
+    }
+
+    filter(&mut list, "length", &mut |e| (min_len..=max_len).contains(&e.word.chars().count()));
+
+    if let Some(chars) = &chars {
+        filter(&mut list, "characters", &mut |e| e.word.to_lowercase().chars().all(|c| chars.contains(c)));
+    }
+
+    if let Some(regex) = &regex {
+        filter(&mut list, "regex", &mut |e| regex.is_match(&e.word));
+    }
+
+    if let Some(blocklist) = &blocklist {
+        filter(&mut list, "blocklist", &mut |e| !blocklist.contains(&fold(&e.word, true)));
+    }
+
+    let mut seen = HashSet::new();
+    filter(&mut list, "duplicates", &mut |e| seen.insert(fold(&e.word, false)));
+
+    match &out {
+        Some(out) => list.save(out)?,
+        None => {
+            let mut stdout = io::stdout().lock();
+            for entry in list.words.iter() {
+                writeln!(stdout, "{}", entry.word)?;
+            }
+        }
+    }
+
+    eprintln!("{}: {} words", path.display(), list.words.len() + removed.iter().map(|r| r.1).sum::<usize>());
+    for (name, count) in removed.iter().filter(|r| r.1 > 0) {
+        eprintln!("  removed {count} by {name}");
+    }
+    eprint!("{}", stats(&list));
+
+    Ok(())
+}
+
+/// word count, lengths and the most common letters of a list
+pub fn stats(list: &WordList) -> String {
+    let lengths: Vec<usize> = list.words.iter().map(|e| e.word.chars().count()).collect();
+    if lengths.is_empty() {
+        return "0 words\n".to_owned();
+    }
+
+    let mut stats = format!(
+        "{} words, {} to {} characters long, {:.1} on average\n",
+        lengths.len(),
+        lengths.iter().min().unwrap(),
+        lengths.iter().max().unwrap(),
+        lengths.iter().sum::<usize>() as f32 / lengths.len() as f32
+    );
+
+    let mut by_length = BTreeMap::new();
+    for length in lengths.iter() {
+        *by_length.entry(*length).or_insert(0) += 1;
+    }
+    for (length, count) in by_length {
+        stats += &format!("  {length:>3}: {count}\n");
+    }
+
+    let mut letters = HashMap::new();
+    for c in list.words.iter().flat_map(|e| e.word.to_lowercase().chars().collect::<Vec<_>>()) {
+        *letters.entry(c).or_insert(0) += 1;
+    }
+    let mut letters: Vec<(char, usize)> = letters.into_iter().collect();
+    letters.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    stats += &format!(
+        "most common letters: {}\n",
+        letters.iter().take(10).map(|(c, n)| format!("{c} {n}")).collect::<Vec<_>>().join(", ")
+    );
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_metadata() {
+        let list = WordList::parse_csv("# name: French\n#license: CC-BY-SA 4.0\n# colour: blue\nword\nbonjour\n").unwrap();
+
+        assert_eq!(list.name.as_deref(), Some("French"));
+        assert_eq!(list.license.as_deref(), Some("CC-BY-SA 4.0"));
+        assert_eq!(list.description, None);
+        assert_eq!(list.words, vec![WordEntry::new("bonjour")]);
+
+        assert!(WordList::parse_csv("# no colon\nword\nbonjour").is_err());
+        assert!(WordList::parse_csv("# name: French\n").is_err());
+    }
+
+    #[test]
+    fn csv_quoted_fields() {
+        let csv = "word,frequency,tags\n\"c'est-à-dire, enfin\",3,\"a;b\"\n \"say \"\"hi\"\"\" , 2 ,\nplain,1,\"\"\n";
+        let list = WordList::parse_csv(csv).unwrap();
+
+        let words: Vec<&str> = list.words.iter().map(|e| e.word.as_str()).collect();
+        assert_eq!(words, ["c'est-à-dire, enfin", "say \"hi\"", "plain"]);
+        assert_eq!(list.words[0].tags, ["a", "b"]);
+        assert_eq!(list.words[1].frequency, Some(2.0));
+        assert!(list.words[2].tags.is_empty());
+    }
+
+    #[test]
+    fn csv_missing_columns() {
+        let list = WordList::parse_csv("tags,word\nfood,apple\n").unwrap();
+        assert_eq!(list.words[0].word, "apple");
+        assert_eq!(list.words[0].frequency, None);
+        assert_eq!(list.words[0].difficulty, None);
+
+        // trailing fields can be left out, and empty ones are unset
+        let list = WordList::parse_csv("word,frequency,difficulty\napple\npear,,0.5\n").unwrap();
+        assert_eq!((list.words[0].frequency, list.words[0].difficulty), (None, None));
+        assert_eq!((list.words[1].frequency, list.words[1].difficulty), (None, Some(0.5)));
+    }
+
+    #[test]
+    fn csv_malformed_rows() {
+        for csv in [
+            "frequency\n3\n",
+            "word,frequency\napple,often\n",
+            "word,difficulty\napple,0.5\n,0.2\n",
+            "word\n\"apple\n",
+            "word\n\"apple\" pie\n",
+            "word,frequency\napple,1,2\n"
+        ] {
+            assert!(WordList::parse_csv(csv).is_err(), "{csv:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let list = WordList {
+            name: Some("Tricky".to_owned()),
+            language: Some("fr".to_owned()),
+            words: vec![
+                WordEntry { word: "a, b".to_owned(), frequency: Some(1.5), tags: vec!["x".to_owned(), "y\"z".to_owned()], ..Default::default() },
+                WordEntry { word: "plain".to_owned(), difficulty: Some(0.25), language: Some("en".to_owned()), ..Default::default() }
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(WordList::parse_csv(&list.to_csv()).unwrap(), list);
+    }
+}